@@ -1,4 +1,12 @@
 @echo off
 if not exist "shaders-cache" mkdir "shaders-cache"
 "%VULKAN_SDK%\Bin\glslc.exe" shaders\shader.vert -o shaders-cache\vert.spv
-"%VULKAN_SDK%\Bin\glslc.exe" shaders\shader.frag -o shaders-cache\frag.spv
\ No newline at end of file
+"%VULKAN_SDK%\Bin\glslc.exe" shaders\shader.frag -o shaders-cache\frag.spv
+rem The library's own shaders are committed, so crates using it build without the Vulkan SDK
+"%VULKAN_SDK%\Bin\glslc.exe" shaders\ui.vert -o shaders\builtin\ui_vert.spv
+"%VULKAN_SDK%\Bin\glslc.exe" shaders\ui.frag -o shaders\builtin\ui_frag.spv
+"%VULKAN_SDK%\Bin\glslc.exe" shaders\debug_lines.vert -o shaders\builtin\debug_lines_vert.spv
+"%VULKAN_SDK%\Bin\glslc.exe" shaders\debug_lines.frag -o shaders\builtin\debug_lines_frag.spv
+"%VULKAN_SDK%\Bin\glslc.exe" shaders\particles.comp -o shaders-cache\particles_comp.spv
+"%VULKAN_SDK%\Bin\glslc.exe" shaders\particles.vert -o shaders-cache\particles_vert.spv
+"%VULKAN_SDK%\Bin\glslc.exe" shaders\particles.frag -o shaders-cache\particles_frag.spv
\ No newline at end of file
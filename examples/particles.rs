@@ -0,0 +1,337 @@
+use tarsier::*;
+
+use ash::vk;
+use ash::util::*;
+
+use std::default::Default;
+use std::io::Cursor;
+
+use nalgebra_glm as glm;
+
+const COMPUTE_SHADER: &[u8] = include_bytes!("../shaders-cache/particles_comp.spv");
+const VERTEX_SHADER: &[u8] = include_bytes!("../shaders-cache/particles_vert.spv");
+const FRAGMENT_SHADER: &[u8] = include_bytes!("../shaders-cache/particles_frag.spv");
+
+const PARTICLE_COUNT: u32 = 1 << 16;
+/// `local_size_x` in particles.comp
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Mirrors `Particle` in particles.comp, std430
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct Particle {
+    /// xyz, seconds left to live
+    position: glm::Vec4,
+    velocity: glm::Vec4
+}
+
+/// Mirrors `ParticlesPushConstants` in particles.comp
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct SimulationSettings {
+    gravity: f32,
+    lifetime: f32,
+    speed: f32,
+    count: u32
+}
+
+/// GPU fountain: a compute pass moves the particles in a storage buffer, which the main pass then draws as points
+/// straight from the same buffer bound as a vertex buffer
+struct Particles {
+    camera: Camera,
+    view_projection: glm::Mat4,
+    settings: SimulationSettings,
+
+    particle_buffer: Buffer,
+
+    descriptor_pool: vk::DescriptorPool,
+    desc_set_layouts: [vk::DescriptorSetLayout; 1],
+    descriptor_sets: Vec<vk::DescriptorSet>,
+
+    simulation: ComputePipeline,
+
+    pipeline_layout: vk::PipelineLayout,
+    graphics_pipeline: vk::Pipeline
+}
+
+impl App for Particles {
+    unsafe fn setup(base: &Base, renderer: &Renderer) -> Self {
+        // ================================================================
+        // PARTICLE BUFFER
+        // ================================================================
+
+        // Everything starts dead, so the first frame emits the whole buffer at once
+        let particles = vec![Particle::default(); PARTICLE_COUNT as usize];
+
+        let particle_buffer = Buffer::new(
+            base,
+            (std::mem::size_of::<Particle>() * particles.len()) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::SharingMode::EXCLUSIVE,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            true
+        );
+
+        let mut slice = Align::new(particle_buffer.ptr.unwrap(), std::mem::align_of::<glm::Vec4>() as u64, particle_buffer.size);
+        slice.copy_from_slice(&particles);
+
+        // Only the GPU writes it from now on
+        particle_buffer.unmap_memory(base);
+
+        // ================================================================
+        // DESCRIPTORS
+        // ================================================================
+
+        let descriptor_sizes = [vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::STORAGE_BUFFER,
+            descriptor_count: 1
+        }];
+        let descriptor_pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(&descriptor_sizes)
+            .max_sets(1)
+            .build();
+        let descriptor_pool = base.device.create_descriptor_pool(&descriptor_pool_info, None).unwrap();
+
+        let desc_layout_bindings = [vk::DescriptorSetLayoutBinding {
+            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            ..Default::default()
+        }];
+        let descriptor_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(&desc_layout_bindings)
+            .build();
+
+        let desc_set_layouts = [
+            base.device.create_descriptor_set_layout(&descriptor_info, None).unwrap()
+        ];
+
+        let desc_alloc_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&desc_set_layouts)
+            .build();
+        let descriptor_sets = base.device.allocate_descriptor_sets(&desc_alloc_info).unwrap();
+
+        let particle_buffer_descriptor = particle_buffer.descriptor();
+
+        let write_desc_sets = [vk::WriteDescriptorSet {
+            dst_set: descriptor_sets[0],
+            descriptor_count: 1,
+            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+            p_buffer_info: &particle_buffer_descriptor,
+            ..Default::default()
+        }];
+        base.device.update_descriptor_sets(&write_desc_sets, &[]);
+
+        // ================================================================
+        // COMPUTE PIPELINE
+        // ================================================================
+
+        let compute_code = read_spv(&mut Cursor::new(COMPUTE_SHADER)).expect("Failed to read compute shader");
+        let simulation = ComputePipeline::new(base, renderer, &compute_code, &desc_set_layouts, None);
+
+        // ================================================================
+        // GRAPHICS PIPELINE
+        // ================================================================
+
+        // Same set 0 and push constant range as everything else, the shaders only read the view projection matrix
+        let vertex_code = read_spv(&mut Cursor::new(VERTEX_SHADER)).expect("Failed to read vertex shader");
+        let vertex_reflection = ShaderReflection::from_spirv(&vertex_code).expect("Failed to reflect vertex shader");
+        let push_constant_ranges = [Globals::push_constant_range(&[&vertex_reflection]).expect("Push constants too large")];
+
+        let set_layouts = [renderer.globals.set_layout];
+        let layout_create_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(&push_constant_ranges)
+            .build();
+        let pipeline_layout = base.device.create_pipeline_layout(&layout_create_info, None).unwrap();
+
+        let vertex_shader_info = vk::ShaderModuleCreateInfo::builder().code(&vertex_code).build();
+        let vertex_shader_module = base.device.create_shader_module(&vertex_shader_info, None).expect("Vertex shader module error");
+
+        let frag_code = read_spv(&mut Cursor::new(FRAGMENT_SHADER)).expect("Failed to read fragment shader");
+        let frag_shader_info = vk::ShaderModuleCreateInfo::builder().code(&frag_code).build();
+        let frag_shader_module = base.device.create_shader_module(&frag_shader_info, None).expect("Fragment shader module error");
+
+        let shader_entry_name = c"main";
+        let shader_stage_create_infos = [
+            vk::PipelineShaderStageCreateInfo {
+                module: vertex_shader_module,
+                p_name: shader_entry_name.as_ptr(),
+                stage: vk::ShaderStageFlags::VERTEX,
+                ..Default::default()
+            },
+            vk::PipelineShaderStageCreateInfo {
+                module: frag_shader_module,
+                p_name: shader_entry_name.as_ptr(),
+                stage: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            }
+        ];
+
+        // The storage buffer read as one vertex per particle
+        let vertex_binding_descriptions = [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: std::mem::size_of::<Particle>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX
+        }];
+
+        let vertex_attribute_descriptions = [
+            vk::VertexInputAttributeDescription { location: 0, binding: 0, format: vk::Format::R32G32B32A32_SFLOAT, offset: 0 },
+            vk::VertexInputAttributeDescription { location: 1, binding: 0, format: vk::Format::R32G32B32A32_SFLOAT, offset: 16 }
+        ];
+
+        let vertex_input_state_info = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_attribute_descriptions(&vertex_attribute_descriptions)
+            .vertex_binding_descriptions(&vertex_binding_descriptions)
+            .build();
+
+        let vertex_input_assembly_state_info = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::POINT_LIST)
+            .build();
+
+        // Both set by the renderer
+        let viewport_state_info = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1)
+            .build();
+
+        let pipeline_state = PipelineState::additive();
+        let rasterization_info = pipeline_state.rasterization_info(base);
+        let depth_state_info = pipeline_state.depth_stencil_info(base);
+        let color_blend_attachment_states = [pipeline_state.color_blend_attachment()];
+
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(&color_blend_attachment_states)
+            .build();
+
+        let multisample_state_info = vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(base.msaa_samples)
+            .build();
+
+        let dynamic_state = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state_info = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_state).build();
+
+        let graphics_pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stage_create_infos)
+            .vertex_input_state(&vertex_input_state_info)
+            .input_assembly_state(&vertex_input_assembly_state_info)
+            .viewport_state(&viewport_state_info)
+            .rasterization_state(&rasterization_info)
+            .multisample_state(&multisample_state_info)
+            .depth_stencil_state(&depth_state_info)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state_info)
+            .layout(pipeline_layout)
+            .render_pass(renderer.renderpass)
+            .build();
+
+        let graphics_pipeline = base
+            .device
+            .create_graphics_pipelines(base.pipeline_cache, &[graphics_pipeline_info], None)
+            .unwrap()[0];
+
+        base.device.destroy_shader_module(vertex_shader_module, None);
+        base.device.destroy_shader_module(frag_shader_module, None);
+
+        // ================================================================
+        // CAMERA
+        // ================================================================
+
+        // Roughly where the fountain goes with the default settings
+        let bounds = Bounds::from_points(&[glm::vec3(-1.0, -1.0, 0.0), glm::vec3(1.0, 1.0, 2.0)]).unwrap();
+        let mut camera = Camera::default();
+        camera.frame(&bounds);
+
+        Particles {
+            camera,
+            view_projection: glm::Mat4::identity(),
+            settings: SimulationSettings {
+                gravity: 2.0,
+                lifetime: 3.0,
+                speed: 3.0,
+                count: PARTICLE_COUNT
+            },
+
+            particle_buffer,
+
+            descriptor_pool,
+            desc_set_layouts,
+            descriptor_sets,
+
+            simulation,
+
+            pipeline_layout,
+            graphics_pipeline
+        }
+    }
+
+    fn update(&mut self, base: &mut Base, frame: &FrameContext) {
+        self.camera.update(frame);
+        self.view_projection = self.camera.projection(base) * self.camera.view();
+    }
+
+    fn ui(&mut self, context: &egui::Context) {
+        egui::Window::new("Particles").show(context, |ui| {
+            ui.add(egui::Slider::new(&mut self.settings.gravity, 0.0..=10.0).text("Gravity"));
+            ui.add(egui::Slider::new(&mut self.settings.lifetime, 0.1..=10.0).text("Lifetime"));
+            ui.add(egui::Slider::new(&mut self.settings.speed, 0.0..=10.0).text("Speed"));
+            ui.add(egui::Slider::new(&mut self.settings.count, 0..=PARTICLE_COUNT).text("Count"));
+
+            ui.collapsing("Camera", |ui| self.camera.ui(ui));
+        });
+    }
+
+    fn compute_passes(&self) -> Vec<ComputePass<'_>> {
+        vec![ComputePass {
+            name: "particles",
+            pipeline: &self.simulation,
+            descriptor_sets: &self.descriptor_sets,
+            push_constants: push_constant_bytes(&self.settings),
+            group_count: [self.settings.count.div_ceil(WORKGROUP_SIZE), 1, 1]
+        }]
+    }
+
+    unsafe fn draw(&self, device: &Device, draw_command_buffer: vk::CommandBuffer) {
+        // Particles past the count are frozen, so they are not drawn either
+        if self.settings.count == 0 {
+            return;
+        }
+
+        device.cmd_bind_pipeline(draw_command_buffer, vk::PipelineBindPoint::GRAPHICS, self.graphics_pipeline);
+        device.cmd_bind_vertex_buffers(draw_command_buffer, 0, &[self.particle_buffer.buffer], &[0]);
+
+        cmd_push_constants(
+            device,
+            draw_command_buffer,
+            self.pipeline_layout,
+            GLOBALS_PUSH_CONSTANT_RANGE.stage_flags,
+            0,
+            &self.view_projection
+        );
+
+        device.cmd_draw(draw_command_buffer, self.settings.count, 1, 0, 0);
+    }
+
+    unsafe fn destroy(&mut self, base: &Base) {
+        base.device.destroy_pipeline(self.graphics_pipeline, None);
+        base.device.destroy_pipeline_layout(self.pipeline_layout, None);
+        self.simulation.destroy(base);
+
+        self.particle_buffer.destroy(base);
+
+        for &descriptor_set_layout in self.desc_set_layouts.iter() {
+            base.device.destroy_descriptor_set_layout(descriptor_set_layout, None);
+        }
+
+        base.device.destroy_descriptor_pool(self.descriptor_pool, None);
+    }
+}
+
+fn main() {
+    run::<Particles>(Settings {
+        samples: vk::SampleCountFlags::TYPE_4,
+        pipeline_cache: Some("./shaders-cache".into()),
+        ..Default::default()
+    });
+}
@@ -0,0 +1,563 @@
+use tarsier::*;
+
+use ash::vk;
+use ash::util::*;
+
+use std::default::Default;
+use std::io::Cursor;
+use std::mem::align_of;
+
+use nalgebra_glm as glm;
+
+// TODO: replace all struct declaration with builders
+
+const VERTEX_SHADER: &[u8] = include_bytes!("../shaders-cache/vert.spv");
+const FRAGMENT_SHADER: &[u8] = include_bytes!("../shaders-cache/frag.spv");
+
+struct VikingRoom {
+    model_path: String,
+    mesh_model: Model,
+    camera: Camera,
+    uniform_data: UniformBufferObject,
+    /// Pushed with every draw instead of living in the uniform buffer
+    model_matrix: glm::Mat4,
+
+    index_buffer: Buffer,
+    vertex_buffer: Buffer,
+    vertex_layout: VertexLayout,
+    uniform_buffer: Buffer,
+    uniform_tweaks: UniformTweaks,
+    /// Used by submeshes without material, the room has none
+    texture: Texture2D,
+    materials: MaterialDescriptors,
+
+    descriptor_pool: vk::DescriptorPool,
+    desc_set_layouts: [vk::DescriptorSetLayout; 1],
+    descriptor_sets: Vec<vk::DescriptorSet>,
+
+    vertex_shader_module: vk::ShaderModule,
+    frag_shader_module: vk::ShaderModule,
+
+    specialization: SpecializationConstants,
+    pipeline_state: PipelineState,
+    /// Set by the UI when a constant or the state changes
+    rebuild_pipeline: bool,
+
+    debug_view: DebugView,
+    debug_lines: DebugLines,
+
+    renderpass: vk::RenderPass,
+    pipeline_layout: vk::PipelineLayout,
+    /// The shaded one, then the wireframe overlay when the device supports it
+    graphics_pipelines: Vec<vk::Pipeline>
+}
+
+impl VikingRoom {
+    /// Called again whenever a specialization constant or the pipeline state changes
+    unsafe fn create_pipelines(&self, base: &Base) -> Vec<vk::Pipeline> {
+        let mut pipelines = vec![self.create_pipeline(base, &self.pipeline_state, &self.specialization)];
+
+        // Drawn over the shaded mesh in a flat color, pulled forward to win the depth test
+        if base.device_features.fill_mode_non_solid != 0 {
+            let state = PipelineState::wireframe().depth_write(false).depth_bias(1.0, 1.0);
+            let mut specialization = self.specialization.clone();
+            specialization.set_value("WIREFRAME", 1.0);
+
+            pipelines.push(self.create_pipeline(base, &state, &specialization));
+        }
+
+        pipelines
+    }
+
+    unsafe fn create_pipeline(&self, base: &Base, state: &PipelineState, specialization: &SpecializationConstants) -> vk::Pipeline {
+        let specialization_info = specialization.info();
+
+        let shader_entry_name = c"main";
+        let shader_stage_create_infos = [
+            vk::PipelineShaderStageCreateInfo {
+                module: self.vertex_shader_module,
+                p_name: shader_entry_name.as_ptr(),
+                p_specialization_info: &specialization_info,
+                stage: vk::ShaderStageFlags::VERTEX,
+                ..Default::default()
+            },
+            vk::PipelineShaderStageCreateInfo {
+                module: self.frag_shader_module,
+                p_name: shader_entry_name.as_ptr(),
+                p_specialization_info: &specialization_info,
+                stage: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            },
+        ];
+
+        // ================================================================
+        // FIXED FUNCTIONS
+        // ================================================================
+
+        let vertex_attribute_descriptions = self.vertex_layout.attribute_descriptions();
+        let vertex_binding_descriptions = self.vertex_layout.binding_descriptions();
+
+        let vertex_input_state_info = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_attribute_descriptions(&vertex_attribute_descriptions)
+            .vertex_binding_descriptions(&vertex_binding_descriptions)
+            .build();
+
+        let vertex_input_assembly_state_info = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(self.mesh_model.topology.to_vk())
+            .primitive_restart_enable(false) // TODO: what does it do?
+            .build();
+
+        // Viewport state, both are dynamic and set by the renderer
+
+        let viewport_state_info = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1)
+            .build();
+
+        // Rasterization, depth and blend states, picked in the UI
+
+        let rasterization_info = state.rasterization_info(base);
+        let depth_state_info = state.depth_stencil_info(base);
+        let color_blend_attachment_states = [state.color_blend_attachment()];
+
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(&color_blend_attachment_states)
+            .build();
+
+        // Multisample state
+
+        let multisample_state_info = vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(base.msaa_samples)
+            .build();
+
+        // ================================================================
+        // PIPELINE
+        // ================================================================
+
+        let dynamic_state = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state_info = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_state).build();
+
+        let graphics_pipeline_infos = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stage_create_infos)
+            .vertex_input_state(&vertex_input_state_info)
+            .input_assembly_state(&vertex_input_assembly_state_info)
+            .viewport_state(&viewport_state_info)
+            .rasterization_state(&rasterization_info)
+            .multisample_state(&multisample_state_info)
+            .depth_stencil_state(&depth_state_info)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state_info)
+            .layout(self.pipeline_layout)
+            .render_pass(self.renderpass)
+            .build();
+
+        // Through the cache, flipping a constant back and forth doesn't compile the same pipeline twice
+        base.device
+            .create_graphics_pipelines(base.pipeline_cache, &[graphics_pipeline_infos], None)
+            .unwrap()[0]
+    }
+}
+
+impl App for VikingRoom {
+    unsafe fn setup(base: &Base, renderer: &Renderer) -> Self {
+        // ================================================================
+        // MODELS
+        // ================================================================
+
+        // Any OBJ, glTF, PLY or STL can be passed on the command line in place of the room
+        let model_path = std::env::args().nth(1).unwrap_or_else(|| "./assets/room/viking_room.obj".to_string());
+        let mesh_model = Model::load(&model_path, &ModelLoadOptions {
+            optimize: true,
+            ..Default::default()
+        }).expect("Failed to load model");
+
+        // ================================================================
+        // INDEX BUFFER
+        // ================================================================
+
+        let index_buffer = Buffer::new(
+            base,
+            (std::mem::size_of::<u32>() * mesh_model.indices.len()) as u64,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+            vk::SharingMode::EXCLUSIVE,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            true
+        );
+
+        let mut slice = Align::new(index_buffer.ptr.unwrap(), align_of::<i32>() as u64, index_buffer.size);
+        slice.copy_from_slice(&mesh_model.indices);
+
+        index_buffer.unmap_memory(base);
+
+        // ================================================================
+        // VERTEX BUFFER
+        // ================================================================
+
+        // Only the attributes the vertex shader declares end up in the buffer
+        let vertex_code = read_spv(&mut Cursor::new(VERTEX_SHADER)).expect("Failed to read vertex shader");
+        let vertex_reflection = ShaderReflection::from_spirv(&vertex_code).expect("Failed to reflect vertex shader");
+        let vertex_layout = VertexLayout::from_reflection(&vertex_reflection).expect("Unsupported vertex shader inputs");
+        let vertex_data = vertex_layout.pack(0, &mesh_model.vertices);
+
+        let vertex_buffer = Buffer::new(
+            base,
+            vertex_data.len() as u64,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::SharingMode::EXCLUSIVE,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            true
+        );
+
+        let mut slice = Align::new(vertex_buffer.ptr.unwrap(), align_of::<f32>() as u64, vertex_buffer.size);
+        slice.copy_from_slice(&vertex_data);
+
+        vertex_buffer.unmap_memory(base);
+
+        // ================================================================
+        // UNIFORM BUFFER
+        // ================================================================
+
+        // Start framing the whole model, whatever its size
+        let bounds = mesh_model.bounds().expect("Model without vertices");
+        let mut camera = Camera::default();
+        camera.frame(&bounds);
+
+        let uniform_color_buffer_data = UniformBufferObject {
+            view: camera.view(),
+            projection: camera.projection(base),
+            tint: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            point_size: 2.0
+        };
+
+        let uniform_buffer = Buffer::new(
+            base,
+            std::mem::size_of_val(&uniform_color_buffer_data) as u64,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::SharingMode::EXCLUSIVE,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            true
+        );
+
+        let mut uniform_aligned_slice = Align::new(
+            uniform_buffer.ptr.unwrap(),
+            align_of::<glm::Vec4>() as u64,
+            uniform_buffer.size
+        );
+        uniform_aligned_slice.copy_from_slice(&[uniform_color_buffer_data]);
+
+        // Stays mapped, the camera matrices are written every frame
+
+        // Members annotated in the shader source get controls in the UI overlay
+        let uniform_block = vertex_reflection
+            .uniform_blocks
+            .iter()
+            .find(|block| block.set == GLOBALS_SET + 1 && block.binding == 0)
+            .expect("Vertex shader without uniform buffer");
+
+        let mut uniform_tweaks = UniformTweaks::new(uniform_block, include_str!("../shaders/shader.vert"));
+        uniform_tweaks.preset_path = Some("./shaders/shader.vert.preset".into());
+
+        // ================================================================
+        // TEXTURES
+        // ================================================================
+
+        let texture = Texture2D::load_from_file(base, "./assets/room/viking_room.png").unwrap();
+
+        // One descriptor set per MTL material, with its maps and colors
+        let materials = MaterialDescriptors::new(base, &mesh_model.materials, &texture);
+
+        // ================================================================
+        // DESCRIPTORS
+        // ================================================================
+
+        let descriptor_sizes = [
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: 1
+            },
+        ];
+        let descriptor_pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(&descriptor_sizes)
+            .max_sets(1)
+            .build();
+        let descriptor_pool = base.device.create_descriptor_pool(&descriptor_pool_info, None).unwrap();
+
+        // HINT: here's where you can change the binding of the buffers to the stages
+        // for example: you can specify samplers working only on fragment, or both fragment and vertex
+        // same thing with the UBO
+        let desc_layout_bindings = [
+            vk::DescriptorSetLayoutBinding {
+                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::VERTEX,
+                ..Default::default()
+            }
+        ];
+        let descriptor_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(&desc_layout_bindings)
+            .build();
+
+        let desc_set_layouts = [
+            base.device.create_descriptor_set_layout(&descriptor_info, None).unwrap()
+        ];
+
+        let desc_alloc_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&desc_set_layouts)
+            .build();
+        let descriptor_sets = base.device.allocate_descriptor_sets(&desc_alloc_info).unwrap();
+
+        let uniform_color_buffer_descriptor = vk::DescriptorBufferInfo {
+            buffer: uniform_buffer.buffer,
+            offset: 0,
+            range: std::mem::size_of_val(&uniform_color_buffer_data) as u64
+        };
+
+        let write_desc_sets = [
+            vk::WriteDescriptorSet {
+                dst_set: descriptor_sets[0],
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                p_buffer_info: &uniform_color_buffer_descriptor,
+                ..Default::default()
+            },
+        ];
+        base.device.update_descriptor_sets(&write_desc_sets, &[]);
+
+        // ================================================================
+        // SHADERS
+        // ================================================================
+
+        let vertex_shader_info = vk::ShaderModuleCreateInfo::builder().code(&vertex_code).build();
+
+        let frag_code = read_spv(&mut Cursor::new(FRAGMENT_SHADER)).expect("Failed to read fragment shader");
+        let frag_shader_info = vk::ShaderModuleCreateInfo::builder().code(&frag_code).build();
+
+        let vertex_shader_module = base.device.create_shader_module(&vertex_shader_info, None).expect("Vertex shader module error");
+        let frag_shader_module = base.device.create_shader_module(&frag_shader_info, None).expect("Fragment shader module error");
+
+        // Constants of both stages, starting from the saved preset if there is one
+        let frag_reflection = ShaderReflection::from_spirv(&frag_code).expect("Failed to reflect fragment shader");
+        let mut specialization = SpecializationConstants::new(&[&vertex_reflection, &frag_reflection]);
+        specialization.preset_path = Some("./shaders/specialization.preset".into());
+
+        if let Some(path) = specialization.preset_path.clone().filter(|path| path.exists()) {
+            specialization.load_preset(&path).expect("Failed to load specialization preset");
+        }
+
+        // The renderer binds its globals at set 0, ours comes right after and the materials last
+        let pipeline_set_layouts = [renderer.globals.set_layout, desc_set_layouts[0], materials.set_layout];
+        // The model matrix goes through push constants, in the range shared with the globals layout so set 0 stays bound
+        let push_constant_ranges = [Globals::push_constant_range(&[&vertex_reflection]).expect("Push constants too large")];
+        let layout_create_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&pipeline_set_layouts)
+            .push_constant_ranges(&push_constant_ranges)
+            .build();
+        let pipeline_layout = base.device.create_pipeline_layout(&layout_create_info, None).unwrap();
+
+        // ================================================================
+        // DEBUG LINES
+        // ================================================================
+
+        // Normals and tangents of every vertex, shown with F3
+        let mut debug_lines = DebugLines::new(base, renderer, mesh_model.vertices.len() * 2);
+        debug_lines.model_normals(&mesh_model, &glm::identity(), bounds.radius * 0.02);
+
+        let mut app = VikingRoom {
+            model_path,
+            mesh_model,
+            camera,
+            uniform_data: uniform_color_buffer_data,
+            model_matrix: glm::identity(),
+
+            index_buffer,
+            vertex_buffer,
+            vertex_layout,
+            uniform_buffer,
+            uniform_tweaks,
+            texture,
+            materials,
+
+            descriptor_pool,
+            desc_set_layouts,
+            descriptor_sets,
+
+            vertex_shader_module,
+            frag_shader_module,
+
+            specialization,
+            pipeline_state: PipelineState::opaque(),
+            rebuild_pipeline: false,
+
+            debug_view: DebugView::default(),
+            debug_lines,
+
+            renderpass: renderer.renderpass,
+            pipeline_layout,
+            graphics_pipelines: vec![]
+        };
+
+        app.graphics_pipelines = app.create_pipelines(base);
+        app
+    }
+
+    fn update(&mut self, base: &mut Base, frame: &FrameContext) {
+        self.camera.update(frame);
+        self.debug_view = *frame.debug_view;
+
+        if self.rebuild_pipeline {
+            self.rebuild_pipeline = false;
+
+            unsafe {
+                base.device.device_wait_idle().unwrap();
+
+                for &pipeline in self.graphics_pipelines.iter() {
+                    base.device.destroy_pipeline(pipeline, None);
+                }
+
+                self.graphics_pipelines = self.create_pipelines(base);
+            }
+        }
+
+        // Uploaded in `draw`, the GPU may still be reading the buffer for the previous frame here
+        self.uniform_data.view = self.camera.view();
+        self.uniform_data.projection = self.camera.projection(base);
+    }
+
+    fn ui(&mut self, context: &egui::Context) {
+        egui::Window::new("Scene").show(context, |ui| {
+            ui.collapsing("Camera", |ui| self.camera.ui(ui));
+
+            ui.collapsing("Uniforms", |ui| {
+                self.uniform_tweaks.ui(ui);
+            });
+
+            ui.collapsing("Pipeline", |ui| {
+                self.rebuild_pipeline |= self.pipeline_state.ui(ui);
+            });
+
+            ui.collapsing("Specialization", |ui| {
+                self.rebuild_pipeline |= self.specialization.ui(ui);
+            });
+
+            ui.collapsing("Assets", |ui| {
+                let model = &self.mesh_model;
+
+                ui.label(&self.model_path);
+                ui.label(format!(
+                    "{} vertices, {} indices, {} submeshes, {} materials, {} LODs",
+                    model.vertices.len(),
+                    model.indices.len(),
+                    model.submeshes.len(),
+                    model.materials.len(),
+                    model.lods.len()
+                ));
+                ui.label(format!("Texture {} x {}", self.texture.data.width, self.texture.data.height));
+            });
+        });
+    }
+
+    unsafe fn draw(&self, device: &Device, draw_command_buffer: vk::CommandBuffer) {
+        // The previous frame is done reading the buffer by now
+        let mut uniform_aligned_slice = Align::new(
+            self.uniform_buffer.ptr.unwrap(),
+            align_of::<glm::Vec4>() as u64,
+            self.uniform_buffer.size
+        );
+        uniform_aligned_slice.copy_from_slice(&[self.uniform_data]);
+
+        // Tint and point size come from the UI
+        self.uniform_tweaks.write(&self.uniform_buffer);
+
+        device.cmd_bind_pipeline(draw_command_buffer, vk::PipelineBindPoint::GRAPHICS, self.graphics_pipelines[0]);
+
+        device.cmd_bind_vertex_buffers(draw_command_buffer, 0, &[self.vertex_buffer.buffer], &[0]);
+        device.cmd_bind_index_buffer(draw_command_buffer, self.index_buffer.buffer, 0, vk::IndexType::UINT32);
+
+        device.cmd_bind_descriptor_sets(
+            draw_command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout,
+            GLOBALS_SET + 1,
+            &self.descriptor_sets[..],
+            &[]
+        );
+
+        cmd_push_constants(
+            device,
+            draw_command_buffer,
+            self.pipeline_layout,
+            GLOBALS_PUSH_CONSTANT_RANGE.stage_flags,
+            0,
+            &self.model_matrix
+        );
+
+        for submesh in self.mesh_model.submeshes.iter() {
+            device.cmd_bind_descriptor_sets(
+                draw_command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                GLOBALS_SET + 2,
+                &[self.materials.descriptor_set(submesh.material)],
+                &[]
+            );
+
+            device.cmd_draw_indexed(
+                draw_command_buffer,
+                submesh.index_count,
+                1, submesh.first_index, 0, 0
+            );
+        }
+
+        // Same buffers, descriptor sets and push constants, the layout is shared
+        if let (true, Some(&wireframe_pipeline)) = (self.debug_view.wireframe, self.graphics_pipelines.get(1)) {
+            device.cmd_bind_pipeline(draw_command_buffer, vk::PipelineBindPoint::GRAPHICS, wireframe_pipeline);
+
+            for submesh in self.mesh_model.submeshes.iter() {
+                device.cmd_draw_indexed(
+                    draw_command_buffer,
+                    submesh.index_count,
+                    1, submesh.first_index, 0, 0
+                );
+            }
+        }
+
+        if self.debug_view.normals {
+            let view_projection = self.uniform_data.projection * self.uniform_data.view * self.model_matrix;
+            self.debug_lines.record(device, draw_command_buffer, &view_projection);
+        }
+    }
+
+    unsafe fn destroy(&mut self, base: &Base) {
+        for &pipeline in self.graphics_pipelines.iter() {
+            base.device.destroy_pipeline(pipeline, None);
+        }
+
+        self.debug_lines.destroy(base);
+
+        base.device.destroy_pipeline_layout(self.pipeline_layout, None);
+        base.device.destroy_shader_module(self.vertex_shader_module, None);
+        base.device.destroy_shader_module(self.frag_shader_module, None);
+
+        self.materials.destroy(base);
+        self.texture.data.destroy(base);
+        self.index_buffer.destroy(base);
+        self.uniform_buffer.destroy(base);
+        self.vertex_buffer.destroy(base);
+
+        for &descriptor_set_layout in self.desc_set_layouts.iter() {
+            base.device.destroy_descriptor_set_layout(descriptor_set_layout, None);
+        }
+
+        base.device.destroy_descriptor_pool(self.descriptor_pool, None);
+    }
+}
+
+fn main() {
+    run::<VikingRoom>(Settings {
+        samples: vk::SampleCountFlags::TYPE_4,
+        pipeline_cache: Some("./shaders-cache".into()),
+        reversed_z: true,
+        ..Default::default()
+    });
+}
@@ -0,0 +1,37 @@
+// Debug views switched with F4 to F6, mirrors DebugViewMode in src/objects/debug_view.rs. Include after globals.glsl
+#define DEBUG_VIEW_SHADED 0u
+#define DEBUG_VIEW_UV_CHECKER 1u
+#define DEBUG_VIEW_DEPTH 2u
+#define DEBUG_VIEW_MIP_LEVEL 3u
+
+layout (set = 0, binding = 1) uniform sampler2D debug_checker;
+
+const vec3 DEBUG_MIP_COLORS[6] = vec3[](
+    vec3(1.0, 0.0, 0.0),
+    vec3(1.0, 0.5, 0.0),
+    vec3(1.0, 1.0, 0.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, 0.5, 1.0),
+    vec3(0.5, 0.0, 1.0)
+);
+
+// `shaded` unless a debug view is active, `lod` is textureQueryLod(main texture, uv).y
+vec4 debug_view_color(vec4 shaded, vec2 uv, float lod) {
+    if (globals.debug_view == DEBUG_VIEW_UV_CHECKER) {
+        return texture(debug_checker, uv);
+    }
+
+    if (globals.debug_view == DEBUG_VIEW_DEPTH) {
+        // Roughly near / distance either way, the curve spreads it over the visible range
+        float depth = globals.reversed_z != 0u ? gl_FragCoord.z : 1.0 - gl_FragCoord.z;
+        return vec4(vec3(pow(depth, 0.25)), 1.0);
+    }
+
+    if (globals.debug_view == DEBUG_VIEW_MIP_LEVEL) {
+        vec3 color = DEBUG_MIP_COLORS[clamp(int(floor(lod)), 0, 5)];
+        float luminance = dot(shaded.rgb, vec3(0.299, 0.587, 0.114));
+        return vec4(color * (0.5 + 0.5 * luminance), 1.0);
+    }
+
+    return shaded;
+}
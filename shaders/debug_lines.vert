@@ -0,0 +1,15 @@
+#version 450
+
+layout (push_constant) uniform DebugLinesPushConstants {
+    mat4 view_projection;
+} lines;
+
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec4 color;
+
+layout (location = 0) out vec4 o_color;
+
+void main() {
+    gl_Position = lines.view_projection * vec4(pos, 1.0);
+    o_color = color;
+}
@@ -0,0 +1,12 @@
+// Bound by the renderer for every draw, mirrors GlobalsUniform in src/objects/globals.rs
+layout (set = 0, binding = 0) uniform Globals {
+    vec4 resolution; // width, height, 1 / width, 1 / height
+    vec4 mouse;      // cursor x, y in pixels, left and right buttons held
+    vec4 date;       // year, month, day, seconds since midnight (UTC)
+    float time;
+    float delta_time;
+    uint frame;
+    float speed;
+    uint debug_view; // see debug.glsl
+    uint reversed_z;
+} globals;
@@ -0,0 +1,19 @@
+#version 450
+
+layout (push_constant) uniform ParticlesDrawPushConstants {
+    mat4 view_projection;
+} draw;
+
+layout (location = 0) in vec4 position;
+layout (location = 1) in vec4 velocity;
+
+layout (location = 0) out vec4 o_color;
+
+void main() {
+    gl_Position = draw.view_projection * vec4(position.xyz, 1.0);
+    gl_PointSize = 2.0;
+
+    // Fast ones are hot, fading out as they die
+    float heat = clamp(length(velocity.xyz) / 4.0, 0.0, 1.0);
+    o_color = vec4(mix(vec3(1.0, 0.3, 0.05), vec3(1.0, 0.9, 0.6), heat), clamp(position.w, 0.0, 1.0));
+}
@@ -1,12 +1,32 @@
 #version 450
 
-layout (binding = 1) uniform sampler2D texSampler;
+#include "globals.glsl"
+#include "debug.glsl"
+
+// Bound per submesh, see MaterialDescriptors in src/objects/material.rs
+layout (set = 2, binding = 0) uniform Material {
+    vec4 ambient;  // optical density in w
+    vec4 diffuse;  // dissolve in w
+    vec4 specular; // shininess in w
+} material;
+layout (set = 2, binding = 1) uniform sampler2D diffuseMap;
+
+// Toggled from the UI, which rebuilds the pipeline instead of recompiling the shader
+layout (constant_id = 0) const bool USE_TEXTURE = true;
+// Set for the wireframe overlay pipeline only
+layout (constant_id = 1) const bool WIREFRAME = false;
 
 layout (location = 0) in vec2 o_uv;
+layout (location = 1) in vec4 o_tint;
 
 layout (location = 0) out vec4 outColor;
 
 void main() {
-    vec4 color = texture(texSampler, o_uv);
-    outColor = color;
+    if (WIREFRAME) {
+        outColor = vec4(1.0, 0.6, 0.1, 1.0);
+        return;
+    }
+
+    vec4 color = (USE_TEXTURE ? texture(diffuseMap, o_uv) : vec4(1.0)) * material.diffuse;
+    outColor = debug_view_color(color * o_tint, o_uv, textureQueryLod(diffuseMap, o_uv).y);
 }
\ No newline at end of file
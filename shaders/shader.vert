@@ -1,17 +1,27 @@
 #version 450
 
-layout (binding = 0) uniform UniformBufferObject {
-    mat4 model;
+#include "globals.glsl"
+
+layout (set = 1, binding = 0) uniform UniformBufferObject {
     mat4 view;
     mat4 projection;
+    vec4 tint; // @color
+    float point_size; // @range(1, 16) @default(2)
 } ubo;
 
+layout (push_constant) uniform ObjectPushConstants {
+    mat4 model;
+} object;
+
 layout (location = 0) in vec3 pos;
 layout (location = 1) in vec2 uv;
 
 layout (location = 0) out vec2 o_uv;
+layout (location = 1) out vec4 o_tint;
 
 void main() {
-    gl_Position = ubo.projection * ubo.view * ubo.model * vec4(pos, 1.0);
+    gl_Position = ubo.projection * ubo.view * object.model * vec4(pos, 1.0);
+    gl_PointSize = ubo.point_size;
     o_uv = uv;
+    o_tint = ubo.tint;
 }
\ No newline at end of file
@@ -0,0 +1,14 @@
+#version 450
+
+// Separate image and sampler, one descriptor set per egui texture
+layout (set = 0, binding = 0) uniform texture2D uiTexture;
+layout (set = 0, binding = 1) uniform sampler uiSampler;
+
+layout (location = 0) in vec2 o_uv;
+layout (location = 1) in vec4 o_color;
+
+layout (location = 0) out vec4 outColor;
+
+void main() {
+    outColor = o_color * texture(sampler2D(uiTexture, uiSampler), o_uv);
+}
@@ -0,0 +1,26 @@
+#version 450
+
+// Mirrors UiPushConstants in src/objects/ui.rs
+layout (push_constant) uniform PushConstants {
+    vec2 screen_size; // in points
+    uint srgb_framebuffer;
+} pc;
+
+layout (location = 0) in vec2 pos;
+layout (location = 1) in vec2 uv;
+layout (location = 2) in vec4 color;
+
+layout (location = 0) out vec2 o_uv;
+layout (location = 1) out vec4 o_color;
+
+vec3 linear_from_srgb(vec3 srgb) {
+    return mix(srgb / 12.92, pow((srgb + 0.055) / 1.055, vec3(2.4)), step(0.04045, srgb));
+}
+
+void main() {
+    gl_Position = vec4(2.0 * pos / pc.screen_size - 1.0, 0.0, 1.0);
+    o_uv = uv;
+
+    // egui colors are premultiplied sRGB, the hardware encodes them back when writing to an sRGB framebuffer
+    o_color = pc.srgb_framebuffer != 0 ? vec4(linear_from_srgb(color.rgb), color.a) : color;
+}
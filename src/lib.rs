@@ -0,0 +1,8 @@
+#![allow(clippy::missing_safety_doc)]
+
+mod objects;
+pub use objects::*;
+
+pub use ash;
+pub use egui;
+pub use nalgebra_glm as glm;
@@ -1,6 +1,9 @@
 mod base;
 pub use base::*;
 
+mod settings;
+pub use settings::*;
+
 mod texture;
 pub use texture::*;
 
@@ -10,5 +13,71 @@ pub use buffer::*;
 mod model;
 pub use model::*;
 
+mod mesh_formats;
+
+mod primitives;
+
+mod mesh_processing;
+pub use mesh_processing::*;
+
+mod mesh_cache;
+pub use mesh_cache::*;
+
+mod input;
+pub use input::*;
+
+mod globals;
+pub use globals::*;
+
+mod camera;
+pub use camera::*;
+
+mod material;
+pub use material::*;
+
+mod scene;
+pub use scene::*;
+
+mod renderer;
+pub use renderer::*;
+
+mod profiler;
+pub use profiler::*;
+
+mod ui;
+pub use ui::*;
+
+mod app;
+pub use app::*;
+
+mod reflection;
+pub use reflection::*;
+
+mod tweaks;
+pub use tweaks::*;
+
+mod preset;
+pub use preset::*;
+
+mod push_constants;
+pub use push_constants::*;
+
+mod specialization;
+pub use specialization::*;
+
+mod pipeline_cache;
+
+mod pipeline_state;
+pub use pipeline_state::*;
+
+mod debug_view;
+pub use debug_view::*;
+
+mod compute;
+pub use compute::*;
+
+mod vertex_layout;
+pub use vertex_layout::*;
+
 mod vertex; pub use vertex:: *;
-mod uniform_buffer_object; pub use uniform_buffer_object::*;
\ No newline at end of file
+mod uniform_buffer_object; pub use uniform_buffer_object::*;
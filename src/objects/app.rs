@@ -0,0 +1,57 @@
+use ash::vk;
+
+use crate::*;
+
+/// Entry point for anything rendered by Tarsier.
+///
+/// `setup` creates the GPU resources once the device and the main render pass exist,
+/// `update` runs once per frame before recording, with the input and timing of that frame,
+/// `compute_passes` are dispatched before the main render pass and `draw` records commands inside it,
+/// seeing everything the compute passes wrote.
+pub trait App {
+    unsafe fn setup(base: &Base, renderer: &Renderer) -> Self where Self: Sized;
+
+    /// Runs while the GPU may still be executing the previous frame, so memory it reads (mapped uniform or
+    /// storage buffers) must not be written here. Keep the new values in the app and upload them in `draw`.
+    fn update(&mut self, _base: &mut Base, _frame: &FrameContext) {}
+
+    /// Adds windows to the UI overlay, called after `update` while the overlay is visible
+    fn ui(&mut self, _context: &egui::Context) {}
+
+    /// Dispatched in order every frame, each one after the previous has finished writing
+    fn compute_passes(&self) -> Vec<ComputePass<'_>> {
+        vec![]
+    }
+
+    /// Called once the previous frame has finished on the GPU, mapped buffers can be written before recording
+    unsafe fn draw(&self, device: &Device, command_buffer: vk::CommandBuffer);
+
+    unsafe fn destroy(&mut self, base: &Base);
+}
+
+/// Opens a window, runs `A` until it is closed and releases every resource.
+pub fn run<A: App>(settings: Settings) {
+    unsafe {
+        let mut base = Base::new(&settings);
+        let mut renderer = Renderer::new(&base);
+        let mut app = A::setup(&base, &renderer);
+
+        base.render_loop(|base, frame| {
+            app.update(base, frame);
+            renderer.globals.update(frame);
+            renderer.ui.run(base, frame, &renderer.profiler, |context| app.ui(context));
+
+            if base.swapchain_outdated {
+                base.recreate_swapchain();
+                renderer.resize(base);
+            }
+
+            renderer.draw_frame(base, &app.compute_passes(), |device, command_buffer| app.draw(device, command_buffer));
+        });
+
+        base.device.device_wait_idle().unwrap();
+
+        app.destroy(&base);
+        renderer.destroy(&base);
+    }
+}
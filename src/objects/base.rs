@@ -16,11 +16,18 @@ use winit::{
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 
 use std::borrow::Cow;
-use std::cell::RefCell;
+use std::time::{Duration, Instant};
 use std::ffi::CStr;
 use std::os::raw::c_char;
 
+use nalgebra_glm as glm;
+
+use crate::{Clock, DebugView, FrameContext, InputState, PresentMode, Settings};
+use super::pipeline_cache::create_pipeline_cache;
+
 pub struct Base {
+    pub settings: Settings,
+
     pub entry: Entry,
     pub instance: Instance,
     pub device: Device,
@@ -30,10 +37,15 @@ pub struct Base {
     pub debug_utils_loader: DebugUtils,
 
     pub window: winit::window::Window,
-    pub event_loop: RefCell<EventLoop<()>>,
+    pub event_loop: Option<EventLoop<()>>,
+    /// Shared with `UiOverlay`, fed with the window events by `render_loop`
+    pub ui_context: egui::Context,
+    pub ui_input: egui_winit::State,
     pub debug_callback: vk::DebugUtilsMessengerEXT,
 
     pub physical_device: vk::PhysicalDevice,
+    pub device_properties: vk::PhysicalDeviceProperties,
+    pub device_features: vk::PhysicalDeviceFeatures,
     pub device_memory_properties: vk::PhysicalDeviceMemoryProperties,
     pub queue_family_index: u32,
     pub present_queue: vk::Queue,
@@ -43,17 +55,28 @@ pub struct Base {
     pub surface_resolution: vk::Extent2D,
 
     pub swapchain: vk::SwapchainKHR,
+    /// Set when the window is resized or the present mode changes, the swapchain gets rebuilt before the next frame
+    pub swapchain_outdated: bool,
     pub present_images: Vec<vk::Image>,
     pub present_image_views: Vec<vk::ImageView>,
 
     pub pool: vk::CommandPool,
+    /// Pass it to every pipeline creation, pipelines rebuilt with the same state are then cheap.
+    /// Loaded from and saved to disk when `Settings::pipeline_cache` is set.
+    pub pipeline_cache: vk::PipelineCache,
     pub draw_command_buffer: vk::CommandBuffer,
     pub setup_command_buffer: vk::CommandBuffer,
 
+    pub depth_format: vk::Format,
     pub depth_image: vk::Image,
     pub depth_image_view: vk::ImageView,
     pub depth_image_memory: vk::DeviceMemory,
 
+    pub msaa_samples: vk::SampleCountFlags,
+    pub color_image: vk::Image,
+    pub color_image_view: vk::ImageView,
+    pub color_image_memory: vk::DeviceMemory,
+
     pub present_complete_semaphore: vk::Semaphore,
     pub rendering_complete_semaphore: vk::Semaphore,
 
@@ -62,23 +85,26 @@ pub struct Base {
 }
 
 impl Base {
-    pub fn new(window_width: u32, window_height: u32) -> Self {
+    pub fn new(settings: &Settings) -> Self {
         unsafe {
             let event_loop = EventLoop::new();
             let window = WindowBuilder::new()
                 .with_title("Tarsier")
                 .with_inner_size(winit::dpi::LogicalSize::new(
-                    f64::from(window_width),
-                    f64::from(window_height)
+                    f64::from(settings.window_width),
+                    f64::from(settings.window_height)
                 ))
                 .build(&event_loop)
                 .unwrap();
+
+            let mut ui_input = egui_winit::State::new(&event_loop);
+            ui_input.set_pixels_per_point(egui_winit::native_pixels_per_point(&window));
             
             let entry = Entry::linked();
-            let app_name = CStr::from_bytes_with_nul_unchecked(b"Tarsier\0");
+            let app_name = c"Tarsier";
 
             let layers_name = [
-                CStr::from_bytes_with_nul_unchecked(b"VK_LAYER_KHRONOS_validation\0")
+                c"VK_LAYER_KHRONOS_validation"
             ];
             let layers_names_raw: Vec<*const c_char> = layers_name.iter().map(|r| r.as_ptr()).collect();
 
@@ -158,8 +184,14 @@ impl Base {
                 Swapchain::name().as_ptr()
             ];
 
+            let supported_features = instance.get_physical_device_features(physical_device);
+
             let features = vk::PhysicalDeviceFeatures {
                 shader_clip_distance: 1,
+                pipeline_statistics_query: supported_features.pipeline_statistics_query,
+                large_points: supported_features.large_points,
+                fill_mode_non_solid: supported_features.fill_mode_non_solid,
+                wide_lines: supported_features.wide_lines,
                 ..Default::default()
             };
 
@@ -183,60 +215,8 @@ impl Base {
                 .get_physical_device_surface_formats(physical_device, surface)
                 .unwrap()[0];
 
-            let surface_capabilities = surface_loader
-                .get_physical_device_surface_capabilities(physical_device, surface)
-                .unwrap();
-
-            let mut desired_image_count = surface_capabilities.min_image_count + 1;
-            if surface_capabilities.max_image_count > 0 && desired_image_count > surface_capabilities.max_image_count {
-                desired_image_count = surface_capabilities.max_image_count;
-            }
-
-            let surface_resolution = match surface_capabilities.current_extent.width {
-                std::u32::MAX => vk::Extent2D {
-                    width: window_width,
-                    height: window_height
-                },
-                _ => surface_capabilities.current_extent
-            };
-
-            let pre_transform = if surface_capabilities.supported_transforms.contains(vk::SurfaceTransformFlagsKHR::IDENTITY) {
-                vk::SurfaceTransformFlagsKHR::IDENTITY
-            } else {
-                surface_capabilities.current_transform
-            };
-
-            let present_modes = surface_loader
-                .get_physical_device_surface_present_modes(physical_device, surface)
-                .unwrap();
-
-            let present_mode = present_modes
-                .iter().cloned()
-                .find(|&mode| mode == vk::PresentModeKHR::MAILBOX)
-                .unwrap_or(vk::PresentModeKHR::FIFO);
-
             let swapchain_loader = Swapchain::new(&instance, &device);
 
-            let swapchain_create_info = vk::SwapchainCreateInfoKHR::builder()
-                .surface(surface)
-                .min_image_count(desired_image_count)
-                .image_color_space(surface_format.color_space)
-                .image_format(surface_format.format)
-                .image_extent(surface_resolution)
-                .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
-                .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
-                .pre_transform(pre_transform)
-                .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-                .present_mode(present_mode)
-                .clipped(true)
-                .image_array_layers(1)
-                .build();
-
-            let swapchain = swapchain_loader.create_swapchain(
-                &swapchain_create_info,
-                None
-            ).unwrap();
-
             let pool_create_info = vk::CommandPoolCreateInfo::builder()
                 .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
                 .queue_family_index(queue_family_index)
@@ -257,69 +237,16 @@ impl Base {
             let setup_command_buffer = command_buffers[0];
             let draw_command_buffer = command_buffers[1];
 
-            let present_images = swapchain_loader.get_swapchain_images(swapchain).unwrap();
-            let present_image_views: Vec<vk::ImageView> = present_images
-                .iter()
-                .map(|&image| {
-                    let create_view_info = vk::ImageViewCreateInfo::builder()
-                        .view_type(vk::ImageViewType::TYPE_2D)
-                        .format(surface_format.format)
-                        .components(vk::ComponentMapping {
-                            r: vk::ComponentSwizzle::R,
-                            g: vk::ComponentSwizzle::G,
-                            b: vk::ComponentSwizzle::B,
-                            a: vk::ComponentSwizzle::A,
-                        })
-                        .subresource_range(vk::ImageSubresourceRange {
-                            aspect_mask: vk::ImageAspectFlags::COLOR,
-                            base_mip_level: 0,
-                            level_count: 1,
-                            base_array_layer: 0,
-                            layer_count: 1
-                        })
-                        .image(image)
-                        .build();
-
-                    device.create_image_view(&create_view_info, None).unwrap()
-                })
-                .collect();
-            
             let device_memory_properties = instance.get_physical_device_memory_properties(physical_device);
 
-            // ================================================================
-            // DEPTH IMAGE
-            // ================================================================
-
-            let depth_image_create_info = vk::ImageCreateInfo::builder()
-                .image_type(vk::ImageType::TYPE_2D)
-                .format(vk::Format::D16_UNORM)
-                .extent(surface_resolution.into())
-                .mip_levels(1)
-                .array_layers(1)
-                .samples(vk::SampleCountFlags::TYPE_1)
-                .tiling(vk::ImageTiling::OPTIMAL)
-                .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
-                .sharing_mode(vk::SharingMode::EXCLUSIVE);
-
-            let depth_image = device.create_image(&depth_image_create_info, None).unwrap();
-            let depth_image_memory_req = device.get_image_memory_requirements(depth_image);
-            let depth_image_memory_index = find_memory_type_index(
-                &depth_image_memory_req, 
-                &device_memory_properties,
-                vk::MemoryPropertyFlags::DEVICE_LOCAL
-            ).expect("Could not find suitable memory index for depth image");
-
-            let depth_image_allocate_info = vk::MemoryAllocateInfo::builder()
-                .allocation_size(depth_image_memory_req.size)
-                .memory_type_index(depth_image_memory_index);
-
-            let depth_image_memory = device
-                .allocate_memory(&depth_image_allocate_info, None)
-                .unwrap();
+            let device_properties = instance.get_physical_device_properties(physical_device);
+            let pipeline_cache = create_pipeline_cache(&device, &device_properties, settings.pipeline_cache.as_deref());
+            ui_input.set_max_texture_side(device_properties.limits.max_image_dimension2_d as usize);
+
+            let msaa_samples = max_usable_sample_count(&device_properties.limits, settings.samples);
 
-            device
-                .bind_image_memory(depth_image, depth_image_memory, 0)
-                .expect("Could not bind depth image memory");
+            let depth_format = find_depth_format(&instance, physical_device, settings.stencil)
+                .expect("Could not find a supported depth format");
 
             let fence_create_info = vk::FenceCreateInfo::builder()
                 .flags(vk::FenceCreateFlags::SIGNALED)
@@ -333,56 +260,6 @@ impl Base {
                 .create_fence(&fence_create_info, None)
                 .expect("Could not create fence");
 
-            record_submit_commandbuffer(
-                &device, 
-                setup_command_buffer, 
-                setup_commands_reuse_fence, 
-                present_queue, 
-                &[], &[], &[], 
-            |device, setup_command_buffer| {
-                let layout_transition_barriers = vk::ImageMemoryBarrier::builder()
-                    .image(depth_image)
-                    .dst_access_mask(
-                        vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
-                    )
-                    .old_layout(vk::ImageLayout::UNDEFINED)
-                    .new_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
-                    .subresource_range(
-                        vk::ImageSubresourceRange::builder()
-                            .aspect_mask(vk::ImageAspectFlags::DEPTH)
-                            .layer_count(1)
-                            .level_count(1)
-                            .build()
-                    )
-                    .build();
-
-                device.cmd_pipeline_barrier(
-                    setup_command_buffer, 
-                    vk::PipelineStageFlags::BOTTOM_OF_PIPE, 
-                    vk::PipelineStageFlags::LATE_FRAGMENT_TESTS, 
-                    vk::DependencyFlags::empty(), 
-                    &[], &[], 
-                    &[layout_transition_barriers]
-                );
-            });
-
-            let depth_image_view_info = vk::ImageViewCreateInfo::builder()
-                .subresource_range(
-                    vk::ImageSubresourceRange::builder()
-                        .aspect_mask(vk::ImageAspectFlags::DEPTH)
-                        .level_count(1)
-                        .layer_count(1)
-                        .build()
-                )
-                .image(depth_image)
-                .format(depth_image_create_info.format)
-                .view_type(vk::ImageViewType::TYPE_2D)
-                .build();
-
-            let depth_image_view = device
-                .create_image_view(&depth_image_view_info, None)
-                .unwrap();
-
             let semaphore_create_info = vk::SemaphoreCreateInfo::default();
 
             let present_complete_semaphore = device
@@ -393,31 +270,44 @@ impl Base {
                 .create_semaphore(&semaphore_create_info, None)
                 .unwrap();
 
-            Base {
-                event_loop: RefCell::new(event_loop),
+            let mut base = Base {
+                settings: settings.clone(),
+                event_loop: Some(event_loop),
+                ui_context: egui::Context::default(),
+                ui_input,
 
                 entry, instance, device,
                 queue_family_index,
                 physical_device,
+                device_properties,
+                device_features: features,
                 device_memory_properties,
                 
                 window,
                 surface_loader,
                 surface_format,
                 present_queue,
-                surface_resolution,
+                surface_resolution: vk::Extent2D::default(),
                 swapchain_loader,
-                swapchain,
-                present_images,
-                present_image_views,
+                swapchain: vk::SwapchainKHR::null(),
+                swapchain_outdated: false,
+                present_images: vec![],
+                present_image_views: vec![],
                 pool,
+                pipeline_cache,
                 
                 draw_command_buffer,
                 setup_command_buffer,
 
-                depth_image,
-                depth_image_memory,
-                depth_image_view,
+                depth_format,
+                depth_image: vk::Image::null(),
+                depth_image_memory: vk::DeviceMemory::null(),
+                depth_image_view: vk::ImageView::null(),
+
+                msaa_samples,
+                color_image: vk::Image::null(),
+                color_image_memory: vk::DeviceMemory::null(),
+                color_image_view: vk::ImageView::null(),
 
                 present_complete_semaphore,
                 rendering_complete_semaphore,
@@ -429,71 +319,304 @@ impl Base {
 
                 debug_callback,
                 debug_utils_loader,
+            };
+
+            base.create_swapchain();
+            base
+        }
+    }
+
+    /// Calls `f` once per frame until the window is closed, along with the input and timing of that frame.
+    /// The playback keys of `Clock` and the debug view keys of `DebugView` are handled here.
+    pub fn render_loop<F: FnMut(&mut Base, &FrameContext)>(&mut self, mut f: F) {
+        let mut event_loop = self.event_loop.take().expect("Render loop is already running");
+        let mut next_frame = Instant::now();
+
+        let mut input = InputState::default();
+        let mut clock = Clock::default();
+        let mut debug_view = DebugView::default();
+        let mut events: Vec<WindowEvent<'static>> = vec![];
+        let mut frame_index = 0;
+        let start = Instant::now();
+        let mut last_frame: Option<Instant> = None;
+
+        event_loop.run_return(|event, _, control_flow| {
+            match event {
+                Event::WindowEvent { event, .. } => {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+
+                        // On esc -> close the window, unless the UI has the keyboard
+                        WindowEvent::KeyboardInput {
+                            input:
+                                KeyboardInput {
+                                    state: ElementState::Pressed,
+                                    virtual_keycode: Some(VirtualKeyCode::Escape),
+                                    ..
+                                },
+                            ..
+                        } if !input.ui_wants_keyboard => *control_flow = ControlFlow::Exit,
+
+                        // On resize -> rebuild swapchain
+                        WindowEvent::Resized(_) => self.swapchain_outdated = true,
+
+                        _ => ()
+                    }
+
+                    // Every event still reaches the input state, so releases over the UI don't leave keys stuck.
+                    // Whether the UI wanted it is exposed through `InputState::ui_wants_pointer` and `ui_wants_keyboard`.
+                    let _ = self.ui_input.on_event(&self.ui_context, &event);
+                    input.handle_event(&event);
+
+                    // Everything is queued for the app, except scale factor changes which borrow the new size
+                    if let Some(event) = event.to_static() {
+                        events.push(event);
+                    }
+                },
+
+                // On clear -> call render loop, unless minimized or waiting for the frame rate cap
+                Event::MainEventsCleared => {
+                    let size = self.window.inner_size();
+                    if size.width == 0 || size.height == 0 {
+                        *control_flow = ControlFlow::Wait;
+                        return;
+                    }
+
+                    let now = Instant::now();
+                    if now < next_frame {
+                        *control_flow = ControlFlow::WaitUntil(next_frame);
+                        return;
+                    }
+
+                    // Based on the last UI frame, the app decides whether to ignore the input
+                    input.ui_wants_pointer = self.ui_context.wants_pointer_input();
+                    input.ui_wants_keyboard = self.ui_context.wants_keyboard_input();
+
+                    let delta_time = last_frame.map_or(Duration::ZERO, |last_frame| now - last_frame);
+                    clock.update(&input, delta_time);
+                    debug_view.update(&input);
+
+                    let frame = FrameContext {
+                        input: &input,
+                        events: &events,
+                        delta_time,
+                        time: now - start,
+                        frame_index,
+                        clock: &clock,
+                        debug_view: &debug_view,
+                        window_size: vk::Extent2D { width: size.width, height: size.height }
+                    };
+
+                    f(self, &frame);
+
+                    input.end_frame();
+                    events.clear();
+                    frame_index += 1;
+                    last_frame = Some(now);
+
+                    match self.settings.max_frame_rate {
+                        Some(max_frame_rate) => {
+                            // Catch up from `now` if we fell behind, so a slow frame doesn't cause a burst of fast ones
+                            next_frame = (next_frame + Duration::from_secs_f64(1.0 / max_frame_rate.get() as f64)).max(now);
+                            *control_flow = ControlFlow::WaitUntil(next_frame);
+                        },
+                        None => *control_flow = ControlFlow::Poll
+                    }
+                },
+
+                _ => (),
             }
+        });
+
+        self.event_loop = Some(event_loop);
+    }
+
+    /// Perspective projection matching the swapchain aspect ratio, the Vulkan clip space and `settings.reversed_z`
+    pub fn projection(&self, fovy: f32, near: f32, far: f32) -> glm::Mat4 {
+        let aspect = (self.surface_resolution.width as f32) / (self.surface_resolution.height as f32);
+
+        let mut projection = if self.settings.reversed_z {
+            glm::reversed_perspective_rh_zo(aspect, fovy, near, far)
+        } else {
+            glm::perspective_rh_zo(aspect, fovy, near, far)
+        };
+
+        // glm was designed for OpenGL, so the Y axis has to be flipped
+        projection[(1, 1)] *= -1.0;
+        projection
+    }
+
+    pub fn depth_compare_op(&self) -> vk::CompareOp {
+        if self.settings.reversed_z {
+            vk::CompareOp::GREATER_OR_EQUAL
+        } else {
+            vk::CompareOp::LESS_OR_EQUAL
         }
     }
 
-    pub fn render_loop<F: Fn()>(&self, f: F) {
-        // let present_info = vk::PresentInfoKHR::builder()
-        //     .wait_semaphores(&[self.rendering_complete_semaphore])
-        //     .swapchains(&[self.swapchain])
-        //     .image_indices(&[0]) // TODO: this value should be the swapimage index
-        //     .build();
-
-        // let result = unsafe {
-        //     self.swapchain_loader.queue_present(self.present_queue, &present_info).unwrap();
-        // }
-        // println!("queue present result: {}", result);
-
-        // TODO: handle minimize to icon case too
-        self.event_loop
-            .borrow_mut()
-            .run_return(|event, _, control_flow| {
-                *control_flow = ControlFlow::Poll;
-                match event {
-                    // On esc -> close the window
-                    Event::WindowEvent {
-                        event:
-                            WindowEvent::CloseRequested | WindowEvent::KeyboardInput {
-                                input:
-                                    KeyboardInput {
-                                        state: ElementState::Pressed,
-                                        virtual_keycode: Some(VirtualKeyCode::Escape),
-                                        ..
-                                    },
-                                ..
-                            },
-                        ..
-                    } => *control_flow = ControlFlow::Exit,
-
-                    // On resize -> rebuild swapchain
-                    Event::WindowEvent {
-                        event:
-                            WindowEvent::Resized(_),
-                        ..
-                    } => println!("Window resized!"),
-
-                    // On clear -> call render loop
-                    Event::MainEventsCleared => f(),
-
-                    _ => (),
-                }
-            });
+    pub fn depth_clear_value(&self) -> f32 {
+        if self.settings.reversed_z { 0.0 } else { 1.0 }
+    }
+
+    /// Changes the present mode, the swapchain is rebuilt before the next frame
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        if self.settings.present_mode != present_mode {
+            self.settings.present_mode = present_mode;
+            self.swapchain_outdated = true;
+        }
     }
 
     pub unsafe fn recreate_swapchain(&mut self) {
         self.device.device_wait_idle().unwrap();
 
-        // Cleanup
+        self.destroy_swapchain_resources();
+        self.create_swapchain();
+    }
+
+    /// Creates the swapchain, retiring the current one if any, along with its image views and the frame attachments
+    unsafe fn create_swapchain(&mut self) {
+        let surface_capabilities = self.surface_loader
+            .get_physical_device_surface_capabilities(self.physical_device, self.surface)
+            .unwrap();
+
+        let mut desired_image_count = surface_capabilities.min_image_count + 1;
+        if surface_capabilities.max_image_count > 0 && desired_image_count > surface_capabilities.max_image_count {
+            desired_image_count = surface_capabilities.max_image_count;
+        }
+
+        let surface_resolution = match surface_capabilities.current_extent.width {
+            u32::MAX => vk::Extent2D {
+                width: self.window.inner_size().width,
+                height: self.window.inner_size().height
+            },
+            _ => surface_capabilities.current_extent
+        };
+
+        let pre_transform = if surface_capabilities.supported_transforms.contains(vk::SurfaceTransformFlagsKHR::IDENTITY) {
+            vk::SurfaceTransformFlagsKHR::IDENTITY
+        } else {
+            surface_capabilities.current_transform
+        };
+
+        let present_modes = self.surface_loader
+            .get_physical_device_surface_present_modes(self.physical_device, self.surface)
+            .unwrap();
+
+        // FIFO is the only mode the spec guarantees
+        let present_mode = present_modes
+            .iter().cloned()
+            .find(|&mode| mode == self.settings.present_mode.to_vk())
+            .unwrap_or(vk::PresentModeKHR::FIFO);
+
+        let old_swapchain = self.swapchain;
+
+        let swapchain_create_info = vk::SwapchainCreateInfoKHR::builder()
+            .surface(self.surface)
+            .min_image_count(desired_image_count)
+            .image_color_space(self.surface_format.color_space)
+            .image_format(self.surface_format.format)
+            .image_extent(surface_resolution)
+            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+            .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .pre_transform(pre_transform)
+            .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+            .present_mode(present_mode)
+            .clipped(true)
+            .image_array_layers(1)
+            .old_swapchain(old_swapchain)
+            .build();
+
+        self.swapchain = self.swapchain_loader.create_swapchain(
+            &swapchain_create_info,
+            None
+        ).unwrap();
+
+        if old_swapchain != vk::SwapchainKHR::null() {
+            self.swapchain_loader.destroy_swapchain(old_swapchain, None);
+        }
+
+        self.surface_resolution = surface_resolution;
+        self.swapchain_outdated = false;
+
+        self.present_images = self.swapchain_loader.get_swapchain_images(self.swapchain).unwrap();
+        self.present_image_views = self.present_images
+            .iter()
+            .map(|&image| {
+                let create_view_info = vk::ImageViewCreateInfo::builder()
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(self.surface_format.format)
+                    .components(vk::ComponentMapping {
+                        r: vk::ComponentSwizzle::R,
+                        g: vk::ComponentSwizzle::G,
+                        b: vk::ComponentSwizzle::B,
+                        a: vk::ComponentSwizzle::A,
+                    })
+                    .subresource_range(vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer: 0,
+                        layer_count: 1
+                    })
+                    .image(image)
+                    .build();
+
+                self.device.create_image_view(&create_view_info, None).unwrap()
+            })
+            .collect();
+
+        // ================================================================
+        // DEPTH IMAGE
+        // ================================================================
+
+        // No layout transition needed: the render pass starts from UNDEFINED and clears it every frame
+        let depth_aspect_mask = if has_stencil_component(self.depth_format) {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        } else {
+            vk::ImageAspectFlags::DEPTH
+        };
+
+        (self.depth_image, self.depth_image_memory, self.depth_image_view) = create_attachment_image(
+            &self.device,
+            &self.device_memory_properties,
+            self.depth_format,
+            surface_resolution,
+            self.msaa_samples,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            depth_aspect_mask
+        );
+
+        // ================================================================
+        // MULTISAMPLED COLOR IMAGE
+        // ================================================================
+
+        // Only needed when multisampling: it gets resolved to the swapchain image at the end of the render pass
+        if self.msaa_samples != vk::SampleCountFlags::TYPE_1 {
+            (self.color_image, self.color_image_memory, self.color_image_view) = create_attachment_image(
+                &self.device,
+                &self.device_memory_properties,
+                self.surface_format.format,
+                surface_resolution,
+                self.msaa_samples,
+                vk::ImageUsageFlags::TRANSIENT_ATTACHMENT | vk::ImageUsageFlags::COLOR_ATTACHMENT,
+                vk::ImageAspectFlags::COLOR
+            );
+        }
+    }
+
+    /// Destroys everything `create_swapchain` creates except for the swapchain itself
+    unsafe fn destroy_swapchain_resources(&mut self) {
         self.device.free_memory(self.depth_image_memory, None);
         self.device.destroy_image_view(self.depth_image_view, None);
         self.device.destroy_image(self.depth_image, None);
-        // TODO: not complete! Will trigger errors here
 
-        let dimensions = [
-            self.window.inner_size().width,
-            self.window.inner_size().height
-        ];
+        self.device.free_memory(self.color_image_memory, None);
+        self.device.destroy_image_view(self.color_image_view, None);
+        self.device.destroy_image(self.color_image, None);
+
+        for &image_view in self.present_image_views.iter() {
+            self.device.destroy_image_view(image_view, None);
+        }
     }
 }
 
@@ -506,15 +629,15 @@ impl Drop for Base {
             self.device.destroy_fence(self.draw_commands_reuse_fence, None);
             self.device.destroy_fence(self.setup_commands_reuse_fence, None);
 
-            self.device.free_memory(self.depth_image_memory, None);
-            self.device.destroy_image_view(self.depth_image_view, None);
-            self.device.destroy_image(self.depth_image, None);
+            self.destroy_swapchain_resources();
+
+            self.device.destroy_command_pool(self.pool, None);
 
-            for &image_view in self.present_image_views.iter() {
-                self.device.destroy_image_view(image_view, None);
+            if let Some(directory) = &self.settings.pipeline_cache {
+                self.save_pipeline_cache(directory);
             }
 
-            self.device.destroy_command_pool(self.pool, None);
+            self.device.destroy_pipeline_cache(self.pipeline_cache, None);
             self.swapchain_loader.destroy_swapchain(self.swapchain, None);
             self.device.destroy_device(None);
             self.surface_loader.destroy_surface(self.surface, None);
@@ -539,6 +662,115 @@ pub fn find_memory_type_index(
         .map(|(index, _)| index as _)
 }
 
+pub unsafe fn find_depth_format(
+    instance: &Instance,
+    physical_device: vk::PhysicalDevice,
+    stencil: bool
+) -> Option<vk::Format> {
+    let candidates: &[vk::Format] = if stencil {
+        &[vk::Format::D24_UNORM_S8_UINT, vk::Format::D32_SFLOAT_S8_UINT]
+    } else {
+        &[vk::Format::D32_SFLOAT, vk::Format::D24_UNORM_S8_UINT, vk::Format::D32_SFLOAT_S8_UINT]
+    };
+
+    candidates
+        .iter()
+        .cloned()
+        .find(|&format| {
+            instance
+                .get_physical_device_format_properties(physical_device, format)
+                .optimal_tiling_features
+                .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+        })
+}
+
+pub fn has_stencil_component(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::D16_UNORM_S8_UINT | vk::Format::D24_UNORM_S8_UINT | vk::Format::D32_SFLOAT_S8_UINT | vk::Format::S8_UINT
+    )
+}
+
+pub fn max_usable_sample_count(
+    limits: &vk::PhysicalDeviceLimits,
+    requested: vk::SampleCountFlags
+) -> vk::SampleCountFlags {
+    let supported = limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+
+    [
+        vk::SampleCountFlags::TYPE_64,
+        vk::SampleCountFlags::TYPE_32,
+        vk::SampleCountFlags::TYPE_16,
+        vk::SampleCountFlags::TYPE_8,
+        vk::SampleCountFlags::TYPE_4,
+        vk::SampleCountFlags::TYPE_2
+    ]
+        .iter()
+        .cloned()
+        .find(|&count| count.as_raw() <= requested.as_raw() && supported.contains(count))
+        .unwrap_or(vk::SampleCountFlags::TYPE_1)
+}
+
+pub unsafe fn create_attachment_image(
+    device: &Device,
+    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    samples: vk::SampleCountFlags,
+    usage: vk::ImageUsageFlags,
+    aspect_mask: vk::ImageAspectFlags
+) -> (vk::Image, vk::DeviceMemory, vk::ImageView) {
+    let image_create_info = vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(format)
+        .extent(extent.into())
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(samples)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(usage)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE);
+
+    let image = device.create_image(&image_create_info, None).unwrap();
+    let image_memory_req = device.get_image_memory_requirements(image);
+    let image_memory_index = find_memory_type_index(
+        &image_memory_req,
+        device_memory_properties,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL
+    ).expect("Could not find suitable memory index for attachment image");
+
+    let image_allocate_info = vk::MemoryAllocateInfo::builder()
+        .allocation_size(image_memory_req.size)
+        .memory_type_index(image_memory_index);
+
+    let image_memory = device
+        .allocate_memory(&image_allocate_info, None)
+        .unwrap();
+
+    device
+        .bind_image_memory(image, image_memory, 0)
+        .expect("Could not bind attachment image memory");
+
+    let image_view_info = vk::ImageViewCreateInfo::builder()
+        .subresource_range(
+            vk::ImageSubresourceRange::builder()
+                .aspect_mask(aspect_mask)
+                .level_count(1)
+                .layer_count(1)
+                .build()
+        )
+        .image(image)
+        .format(format)
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .build();
+
+    let image_view = device
+        .create_image_view(&image_view_info, None)
+        .unwrap();
+
+    (image, image_memory, image_view)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn record_submit_commandbuffer<F: FnOnce(&Device, vk::CommandBuffer)>(
     device: &Device,
@@ -552,7 +784,7 @@ pub fn record_submit_commandbuffer<F: FnOnce(&Device, vk::CommandBuffer)>(
 ) {
     unsafe {
         device
-            .wait_for_fences(&[command_buffer_reuse_fence], true, std::u64::MAX)
+            .wait_for_fences(&[command_buffer_reuse_fence], true, u64::MAX)
             .expect("Wait for fence failed");
 
         device
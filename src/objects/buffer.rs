@@ -57,6 +57,15 @@ impl Buffer {
         }
     }
 
+    /// The whole buffer, for `UNIFORM_BUFFER` and `STORAGE_BUFFER` descriptors
+    pub fn descriptor(&self) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.buffer,
+            offset: 0,
+            range: vk::WHOLE_SIZE
+        }
+    }
+
     pub unsafe fn unmap_memory(&self, base: &Base) {
         base.device.unmap_memory(self.device_memory);
     }
@@ -0,0 +1,171 @@
+use std::f32::consts::FRAC_PI_2;
+
+use nalgebra_glm as glm;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::*;
+
+/// Keeps the view direction away from the up axis, where `look_at` degenerates
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Left drag rotates around the target, middle drag pans, scroll zooms
+    Orbit,
+    /// Left drag looks around, WASD moves, Q/E go down/up, shift speeds up
+    Fly
+}
+
+/// Z-up camera placed at `distance` from `target`, in the direction given by `yaw` (around Z) and `pitch` (above the XY plane).
+/// Both modes share this state, so switching with Tab keeps the current view.
+pub struct Camera {
+    pub mode: CameraMode,
+
+    pub target: glm::Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+
+    pub fovy: f32,
+    pub near: f32,
+    pub far: f32,
+
+    /// Radians per pixel of mouse movement
+    pub sensitivity: f32,
+    /// Fly mode movement, in units per second
+    pub speed: f32
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            mode: CameraMode::Orbit,
+
+            target: glm::vec3(0.0, 0.0, 0.0),
+            distance: 3.5,
+            yaw: glm::quarter_pi(),
+            pitch: 0.6,
+
+            fovy: glm::radians(&glm::vec1(45.0))[0],
+            near: 0.1,
+            far: 100.0,
+
+            sensitivity: 0.005,
+            speed: 2.0
+        }
+    }
+}
+
+impl Camera {
+    /// Centers the bounds and moves back until they fill the view, clip planes and fly speed follow the model size
+    pub fn frame(&mut self, bounds: &Bounds) {
+        let radius = bounds.radius.max(f32::EPSILON);
+
+        self.target = bounds.center;
+        self.distance = bounds.framing_distance(self.fovy);
+        self.near = radius / 100.0;
+        self.far = self.distance * 10.0 + radius;
+        self.speed = radius;
+    }
+
+    /// Applies the mouse and keyboard input of the frame, unless the UI overlay wants it
+    pub fn update(&mut self, frame: &FrameContext) {
+        let input = frame.input;
+
+        if !input.ui_wants_pointer {
+            self.drag(input, input.cursor_delta);
+            self.distance = (self.distance * 0.9f32.powf(input.scroll_delta.y)).max(self.near);
+        }
+
+        if input.ui_wants_keyboard {
+            return;
+        }
+
+        if input.key_pressed(VirtualKeyCode::Tab) {
+            self.mode = match self.mode {
+                CameraMode::Orbit => CameraMode::Fly,
+                CameraMode::Fly => CameraMode::Orbit
+            };
+        }
+
+        if self.mode != CameraMode::Fly {
+            return;
+        }
+
+        let key = |key: VirtualKeyCode| if input.key_down(key) { 1.0 } else { 0.0 };
+
+        let forward = -self.direction();
+        let right = forward.cross(&glm::Vec3::z()).normalize();
+
+        let movement = forward * (key(VirtualKeyCode::W) - key(VirtualKeyCode::S))
+            + right * (key(VirtualKeyCode::D) - key(VirtualKeyCode::A))
+            + glm::Vec3::z() * (key(VirtualKeyCode::E) - key(VirtualKeyCode::Q));
+
+        let boost = if input.modifiers.shift() { 4.0 } else { 1.0 };
+
+        self.target += movement * self.speed * boost * frame.delta_time.as_secs_f32();
+    }
+
+    /// Controls for the UI overlay
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.mode, CameraMode::Orbit, "Orbit");
+            ui.radio_value(&mut self.mode, CameraMode::Fly, "Fly");
+        });
+
+        ui.add(egui::Slider::new(&mut self.fovy, 0.1..=2.5).text("FOV (rad)"));
+        ui.add(egui::Slider::new(&mut self.distance, self.near..=self.far).logarithmic(true).text("Distance"));
+        ui.add(egui::Slider::new(&mut self.sensitivity, 0.0005..=0.02).logarithmic(true).text("Sensitivity"));
+        ui.add(egui::DragValue::new(&mut self.speed).speed(0.1).prefix("Speed "));
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut self.near).speed(0.01).clamp_range(0.0001..=self.far).prefix("Near "));
+            ui.add(egui::DragValue::new(&mut self.far).speed(1.0).clamp_range(self.near..=f32::MAX).prefix("Far "));
+        });
+
+        let (eye, target) = (self.eye(), self.target);
+        ui.label(format!("Eye {:.2} {:.2} {:.2}", eye.x, eye.y, eye.z));
+        ui.label(format!("Target {:.2} {:.2} {:.2}", target.x, target.y, target.z));
+    }
+
+    pub fn eye(&self) -> glm::Vec3 {
+        self.target + self.direction() * self.distance
+    }
+
+    pub fn view(&self) -> glm::Mat4 {
+        glm::look_at(&self.eye(), &self.target, &glm::Vec3::z())
+    }
+
+    pub fn projection(&self, base: &Base) -> glm::Mat4 {
+        base.projection(self.fovy, self.near, self.far)
+    }
+
+    /// From the target towards the eye
+    fn direction(&self) -> glm::Vec3 {
+        glm::vec3(self.pitch.cos() * self.yaw.cos(), self.pitch.cos() * self.yaw.sin(), self.pitch.sin())
+    }
+
+    fn drag(&mut self, input: &InputState, delta: glm::Vec2) {
+        if input.mouse_down(MouseButton::Left) {
+            let eye = self.eye();
+
+            self.yaw -= delta.x * self.sensitivity;
+            self.pitch = (self.pitch + delta.y * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+
+            // Flying looks around from the eye instead of orbiting around the target
+            if self.mode == CameraMode::Fly {
+                self.target = eye - self.direction() * self.distance;
+            }
+        }
+
+        if input.mouse_down(MouseButton::Middle) {
+            let forward = -self.direction();
+            let right = forward.cross(&glm::Vec3::z()).normalize();
+            let up = right.cross(&forward);
+
+            // Scaled so the target follows the cursor at any distance
+            let scale = self.distance * self.sensitivity / 2.0;
+            self.target += (up * delta.y - right * delta.x) * scale;
+        }
+    }
+}
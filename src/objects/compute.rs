@@ -0,0 +1,251 @@
+use ash::vk;
+
+use crate::*;
+
+/// Compute shader with its layout. Set `GLOBALS_SET` holds the renderer globals and `set_layouts` follow from
+/// `GLOBALS_SET + 1`. Push constants use `GLOBALS_PUSH_CONSTANT_RANGE` like graphics pipelines using the globals,
+/// so the layout stays compatible with the one the renderer binds the globals with.
+pub struct ComputePipeline {
+    pub pipeline: vk::Pipeline,
+    pub layout: vk::PipelineLayout
+}
+
+impl ComputePipeline {
+    pub unsafe fn new(
+        base: &Base,
+        renderer: &Renderer,
+        code: &[u32],
+        set_layouts: &[vk::DescriptorSetLayout],
+        specialization: Option<&SpecializationConstants>
+    ) -> Self {
+        let reflection = ShaderReflection::from_spirv(code).expect("Failed to reflect compute shader");
+        let push_constant_ranges = [Globals::push_constant_range(&[&reflection]).expect("Compute shader push constants too large")];
+
+        let mut pipeline_set_layouts = vec![renderer.globals.set_layout];
+        pipeline_set_layouts.extend_from_slice(set_layouts);
+
+        let layout_create_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&pipeline_set_layouts)
+            .push_constant_ranges(&push_constant_ranges)
+            .build();
+        let layout = base.device.create_pipeline_layout(&layout_create_info, None).unwrap();
+
+        let shader_info = vk::ShaderModuleCreateInfo::builder().code(code).build();
+        let shader_module = base.device.create_shader_module(&shader_info, None).expect("Compute shader module error");
+
+        let specialization_info = specialization.map(|specialization| specialization.info());
+
+        let shader_entry_name = c"main";
+        let stage_create_info = vk::PipelineShaderStageCreateInfo {
+            module: shader_module,
+            p_name: shader_entry_name.as_ptr(),
+            p_specialization_info: specialization_info.as_ref().map_or(std::ptr::null(), |info| info as *const _),
+            stage: vk::ShaderStageFlags::COMPUTE,
+            ..Default::default()
+        };
+
+        let pipeline_info = vk::ComputePipelineCreateInfo::builder()
+            .stage(stage_create_info)
+            .layout(layout)
+            .build();
+
+        let pipeline = base
+            .device
+            .create_compute_pipelines(base.pipeline_cache, &[pipeline_info], None)
+            .map_err(|(_, error)| error)
+            .expect("Could not create compute pipeline")[0];
+
+        base.device.destroy_shader_module(shader_module, None);
+
+        ComputePipeline { pipeline, layout }
+    }
+
+    pub unsafe fn destroy(&self, base: &Base) {
+        base.device.destroy_pipeline(self.pipeline, None);
+        base.device.destroy_pipeline_layout(self.layout, None);
+    }
+}
+
+/// A single dispatch, recorded by `Renderer::draw_frame` ahead of the main render pass, see `App::compute_passes`.
+/// Passes run in order, each one sees the writes of the previous ones and the graphics passes see all of them.
+pub struct ComputePass<'a> {
+    /// Shown in the profiler
+    pub name: &'static str,
+    pub pipeline: &'a ComputePipeline,
+    /// Bound from `GLOBALS_SET + 1`
+    pub descriptor_sets: &'a [vk::DescriptorSet],
+    /// Pushed at offset 0 when not empty, at most `GLOBALS_PUSH_CONSTANT_RANGE.size` bytes
+    pub push_constants: &'a [u8],
+    /// Workgroups, not invocations
+    pub group_count: [u32; 3]
+}
+
+impl ComputePass<'_> {
+    pub(crate) unsafe fn record(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, self.pipeline.pipeline);
+
+        if !self.descriptor_sets.is_empty() {
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.pipeline.layout,
+                GLOBALS_SET + 1,
+                self.descriptor_sets,
+                &[]
+            );
+        }
+
+        if !self.push_constants.is_empty() {
+            device.cmd_push_constants(
+                command_buffer,
+                self.pipeline.layout,
+                GLOBALS_PUSH_CONSTANT_RANGE.stage_flags,
+                0,
+                self.push_constants
+            );
+        }
+
+        let [x, y, z] = self.group_count;
+        device.cmd_dispatch(command_buffer, x, y, z);
+    }
+}
+
+/// Writes of a compute pass made visible to the next one
+pub(crate) unsafe fn compute_to_compute_barrier(device: &Device, command_buffer: vk::CommandBuffer) {
+    let barrier = vk::MemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE)
+        .build();
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::DependencyFlags::empty(),
+        &[barrier], &[], &[]
+    );
+}
+
+/// Writes of the compute passes made visible to everything the graphics passes read: vertex and index buffers,
+/// indirect commands, uniforms and shader reads. Storage images stay in the `GENERAL` layout, so no transition.
+pub(crate) unsafe fn compute_to_graphics_barrier(device: &Device, command_buffer: vk::CommandBuffer) {
+    let barrier = vk::MemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+        .dst_access_mask(
+            vk::AccessFlags::INDIRECT_COMMAND_READ
+                | vk::AccessFlags::INDEX_READ
+                | vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+                | vk::AccessFlags::UNIFORM_READ
+                | vk::AccessFlags::SHADER_READ
+        )
+        .build();
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::PipelineStageFlags::DRAW_INDIRECT
+            | vk::PipelineStageFlags::VERTEX_INPUT
+            | vk::PipelineStageFlags::VERTEX_SHADER
+            | vk::PipelineStageFlags::FRAGMENT_SHADER,
+        vk::DependencyFlags::empty(),
+        &[barrier], &[], &[]
+    );
+}
+
+/// 2D image written by compute shaders as a `STORAGE_IMAGE` and sampled by fragment shaders as a
+/// `COMBINED_IMAGE_SAMPLER`, always in the `GENERAL` layout
+pub struct StorageImage {
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    pub memory: vk::DeviceMemory,
+    pub sampler: vk::Sampler,
+
+    pub format: vk::Format,
+    pub extent: vk::Extent2D
+}
+
+impl StorageImage {
+    /// `format` needs storage image support, `R8G8B8A8_UNORM`, `R16G16B16A16_SFLOAT` and `R32G32B32A32_SFLOAT` always have it
+    pub unsafe fn new(base: &Base, format: vk::Format, extent: vk::Extent2D) -> Self {
+        let (image, memory, view) = create_attachment_image(
+            &base.device,
+            &base.device_memory_properties,
+            format,
+            extent,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+            vk::ImageAspectFlags::COLOR
+        );
+
+        record_submit_commandbuffer(
+            &base.device,
+            base.setup_command_buffer,
+            base.setup_commands_reuse_fence,
+            base.present_queue,
+            &[], &[], &[],
+            |device, command_buffer| {
+                let barrier = vk::ImageMemoryBarrier::builder()
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .new_layout(vk::ImageLayout::GENERAL)
+                    .src_access_mask(vk::AccessFlags::empty())
+                    .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE)
+                    .image(image)
+                    .subresource_range(
+                        vk::ImageSubresourceRange::builder()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .level_count(1)
+                            .layer_count(1)
+                            .build()
+                    )
+                    .build();
+
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    vk::DependencyFlags::empty(),
+                    &[], &[], &[barrier]
+                );
+            }
+        );
+
+        let sampler_info = vk::SamplerCreateInfo {
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            max_anisotropy: 1.0,
+            ..Default::default()
+        };
+
+        let sampler = base.device.create_sampler(&sampler_info, None).unwrap();
+
+        StorageImage { image, view, memory, sampler, format, extent }
+    }
+
+    /// For `STORAGE_IMAGE` descriptors
+    pub fn storage_descriptor(&self) -> vk::DescriptorImageInfo {
+        vk::DescriptorImageInfo {
+            image_layout: vk::ImageLayout::GENERAL,
+            image_view: self.view,
+            sampler: vk::Sampler::null()
+        }
+    }
+
+    /// For `COMBINED_IMAGE_SAMPLER` descriptors
+    pub fn sampled_descriptor(&self) -> vk::DescriptorImageInfo {
+        vk::DescriptorImageInfo {
+            image_layout: vk::ImageLayout::GENERAL,
+            image_view: self.view,
+            sampler: self.sampler
+        }
+    }
+
+    pub unsafe fn destroy(&self, base: &Base) {
+        base.device.destroy_sampler(self.sampler, None);
+        base.device.destroy_image_view(self.view, None);
+        base.device.destroy_image(self.image, None);
+        base.device.free_memory(self.memory, None);
+    }
+}
@@ -0,0 +1,266 @@
+use std::io::Cursor;
+
+use ash::util::read_spv;
+use ash::vk;
+use nalgebra_glm as glm;
+use winit::event::VirtualKeyCode;
+
+use crate::*;
+
+const DEBUG_LINES_VERTEX_SHADER: &[u8] = include_bytes!("../../shaders/builtin/debug_lines_vert.spv");
+const DEBUG_LINES_FRAGMENT_SHADER: &[u8] = include_bytes!("../../shaders/builtin/debug_lines_frag.spv");
+
+const NORMAL_COLOR: glm::Vec4 = glm::Vec4::new(0.2, 0.4, 1.0, 1.0);
+const TANGENT_COLOR: glm::Vec4 = glm::Vec4::new(1.0, 0.2, 0.2, 1.0);
+
+/// What the fragment shaders output instead of the shaded color, through `debug_view_color` in `shaders/debug.glsl`.
+/// The values match the `DEBUG_VIEW_*` defines there.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebugViewMode {
+    #[default]
+    Shaded = 0,
+    /// `assets/checker.png` mapped with the mesh UVs
+    UvChecker = 1,
+    /// Brighter when closer
+    Depth = 2,
+    /// One color per mip level the main texture would be sampled at
+    MipLevel = 3
+}
+
+impl DebugViewMode {
+    pub const ALL: [DebugViewMode; 4] = [DebugViewMode::Shaded, DebugViewMode::UvChecker, DebugViewMode::Depth, DebugViewMode::MipLevel];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugViewMode::Shaded => "Shaded",
+            DebugViewMode::UvChecker => "UV checker",
+            DebugViewMode::Depth => "Depth",
+            DebugViewMode::MipLevel => "Mip level"
+        }
+    }
+}
+
+/// Debug visualizations, toggled by `Base::render_loop` with these keys:
+/// - F2 shows the wireframe on top of the shaded mesh
+/// - F3 shows vertex normals and tangents
+/// - F4, F5 and F6 switch to the UV checker, depth and mip level views, the same key again goes back to shaded
+///
+/// The mode reaches the shaders through `GlobalsUniform::debug_view`, the overlays are drawn by the app:
+/// the wireframe with a `PipelineState::wireframe` pipeline, normals with `DebugLines`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DebugView {
+    pub mode: DebugViewMode,
+    pub wireframe: bool,
+    pub normals: bool
+}
+
+impl DebugView {
+    pub(crate) fn update(&mut self, input: &InputState) {
+        if input.ui_wants_keyboard {
+            return;
+        }
+
+        if input.key_pressed(VirtualKeyCode::F2) {
+            self.wireframe = !self.wireframe;
+        }
+
+        if input.key_pressed(VirtualKeyCode::F3) {
+            self.normals = !self.normals;
+        }
+
+        for (key, mode) in [
+            (VirtualKeyCode::F4, DebugViewMode::UvChecker),
+            (VirtualKeyCode::F5, DebugViewMode::Depth),
+            (VirtualKeyCode::F6, DebugViewMode::MipLevel)
+        ] {
+            if input.key_pressed(key) {
+                self.mode = if self.mode == mode { DebugViewMode::Shaded } else { mode };
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct DebugVertex {
+    pub pos: glm::Vec3,
+    pub color: glm::Vec4
+}
+
+/// World space line list drawn on top of the scene, depth tested but not written, inside the main render pass.
+/// Vertices live in a host visible buffer sized for `max_lines` and are uploaded when recording.
+pub struct DebugLines {
+    pub vertices: Vec<DebugVertex>,
+
+    max_lines: usize,
+    vertex_buffer: Buffer,
+
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline
+}
+
+impl DebugLines {
+    pub unsafe fn new(base: &Base, renderer: &Renderer, max_lines: usize) -> Self {
+        let vertex_buffer = Buffer::new(
+            base,
+            (max_lines.max(1) * 2 * std::mem::size_of::<DebugVertex>()) as u64,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::SharingMode::EXCLUSIVE,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            true
+        );
+
+        // View projection matrix
+        let push_constant_ranges = [push_constant_range::<glm::Mat4>(vk::ShaderStageFlags::VERTEX, 0)];
+
+        let layout_create_info = vk::PipelineLayoutCreateInfo::builder()
+            .push_constant_ranges(&push_constant_ranges)
+            .build();
+        let pipeline_layout = base.device.create_pipeline_layout(&layout_create_info, None).unwrap();
+
+        let vertex_code = read_spv(&mut Cursor::new(DEBUG_LINES_VERTEX_SHADER)).expect("Failed to read debug lines vertex shader");
+        let vertex_shader_info = vk::ShaderModuleCreateInfo::builder().code(&vertex_code).build();
+        let vertex_shader_module = base.device.create_shader_module(&vertex_shader_info, None).expect("Debug lines vertex shader module error");
+
+        let frag_code = read_spv(&mut Cursor::new(DEBUG_LINES_FRAGMENT_SHADER)).expect("Failed to read debug lines fragment shader");
+        let frag_shader_info = vk::ShaderModuleCreateInfo::builder().code(&frag_code).build();
+        let frag_shader_module = base.device.create_shader_module(&frag_shader_info, None).expect("Debug lines fragment shader module error");
+
+        let shader_entry_name = c"main";
+        let shader_stage_create_infos = [
+            vk::PipelineShaderStageCreateInfo {
+                module: vertex_shader_module,
+                p_name: shader_entry_name.as_ptr(),
+                stage: vk::ShaderStageFlags::VERTEX,
+                ..Default::default()
+            },
+            vk::PipelineShaderStageCreateInfo {
+                module: frag_shader_module,
+                p_name: shader_entry_name.as_ptr(),
+                stage: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            }
+        ];
+
+        let vertex_binding_descriptions = [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: std::mem::size_of::<DebugVertex>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX
+        }];
+
+        let vertex_attribute_descriptions = [
+            vk::VertexInputAttributeDescription { location: 0, binding: 0, format: vk::Format::R32G32B32_SFLOAT, offset: 0 },
+            vk::VertexInputAttributeDescription { location: 1, binding: 0, format: vk::Format::R32G32B32A32_SFLOAT, offset: 12 }
+        ];
+
+        let vertex_input_state_info = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_attribute_descriptions(&vertex_attribute_descriptions)
+            .vertex_binding_descriptions(&vertex_binding_descriptions)
+            .build();
+
+        let vertex_input_assembly_state_info = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::LINE_LIST)
+            .build();
+
+        // Both set by the renderer
+        let viewport_state_info = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1)
+            .build();
+
+        let pipeline_state = PipelineState::opaque().cull_mode(vk::CullModeFlags::NONE).depth_write(false);
+        let rasterization_info = pipeline_state.rasterization_info(base);
+        let depth_state_info = pipeline_state.depth_stencil_info(base);
+        let color_blend_attachment_states = [pipeline_state.color_blend_attachment()];
+
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(&color_blend_attachment_states)
+            .build();
+
+        let multisample_state_info = vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(base.msaa_samples)
+            .build();
+
+        let dynamic_state = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state_info = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_state).build();
+
+        let graphics_pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stage_create_infos)
+            .vertex_input_state(&vertex_input_state_info)
+            .input_assembly_state(&vertex_input_assembly_state_info)
+            .viewport_state(&viewport_state_info)
+            .rasterization_state(&rasterization_info)
+            .multisample_state(&multisample_state_info)
+            .depth_stencil_state(&depth_state_info)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state_info)
+            .layout(pipeline_layout)
+            .render_pass(renderer.renderpass)
+            .build();
+
+        let pipeline = base
+            .device
+            .create_graphics_pipelines(base.pipeline_cache, &[graphics_pipeline_info], None)
+            .unwrap()[0];
+
+        base.device.destroy_shader_module(vertex_shader_module, None);
+        base.device.destroy_shader_module(frag_shader_module, None);
+
+        DebugLines {
+            vertices: vec![],
+
+            max_lines,
+            vertex_buffer,
+
+            pipeline_layout,
+            pipeline
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn line(&mut self, from: glm::Vec3, to: glm::Vec3, color: glm::Vec4) {
+        self.vertices.push(DebugVertex { pos: from, color });
+        self.vertices.push(DebugVertex { pos: to, color });
+    }
+
+    /// Normals in blue and tangents in red for every vertex of `model`, `length` long in world units
+    pub fn model_normals(&mut self, model: &Model, transform: &glm::Mat4, length: f32) {
+        let normal_matrix = glm::mat4_to_mat3(&glm::transpose(&glm::inverse(transform)));
+        let tangent_matrix = glm::mat4_to_mat3(transform);
+
+        for vertex in model.vertices.iter() {
+            let pos = (transform * vertex.pos.push(1.0)).xyz();
+            let normal = (normal_matrix * vertex.normal).normalize();
+            let tangent = (tangent_matrix * vertex.tangent.xyz()).normalize();
+
+            self.line(pos, pos + normal * length, NORMAL_COLOR);
+            self.line(pos, pos + tangent * length, TANGENT_COLOR);
+        }
+    }
+
+    /// Lines past `max_lines` are dropped. Call from `App::draw`, where the buffer is no longer in use by the GPU.
+    pub unsafe fn record(&self, device: &Device, command_buffer: vk::CommandBuffer, view_projection: &glm::Mat4) {
+        let vertex_count = self.vertices.len().min(self.max_lines * 2);
+
+        if vertex_count == 0 {
+            return;
+        }
+
+        let ptr = self.vertex_buffer.ptr.unwrap() as *mut DebugVertex;
+        std::ptr::copy_nonoverlapping(self.vertices.as_ptr(), ptr, vertex_count);
+
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.vertex_buffer.buffer], &[0]);
+        cmd_push_constants(device, command_buffer, self.pipeline_layout, vk::ShaderStageFlags::VERTEX, 0, view_projection);
+        device.cmd_draw(command_buffer, vertex_count as u32, 1, 0, 0);
+    }
+
+    pub unsafe fn destroy(&self, base: &Base) {
+        base.device.destroy_pipeline(self.pipeline, None);
+        base.device.destroy_pipeline_layout(self.pipeline_layout, None);
+        self.vertex_buffer.destroy(base);
+    }
+}
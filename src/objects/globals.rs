@@ -0,0 +1,404 @@
+use std::error::Error;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ash::vk;
+use nalgebra_glm as glm;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::*;
+
+/// Descriptor set reserved for `GlobalsUniform`, app sets start right after it
+pub const GLOBALS_SET: u32 = 0;
+pub const GLOBALS_BINDING: u32 = 0;
+/// `assets/checker.png`, sampled by the UV checker debug view
+pub const GLOBALS_CHECKER_BINDING: u32 = 1;
+
+/// Push constants of every pipeline layout that includes `GLOBALS_SET`. Vulkan only keeps a set bound across layouts
+/// created with identical push constant ranges, so they all declare this one instead of their own, through
+/// `Globals::push_constant_range`, and push with its stage flags. 128 bytes is the minimum every device supports.
+pub const GLOBALS_PUSH_CONSTANT_RANGE: vk::PushConstantRange = vk::PushConstantRange {
+    stage_flags: vk::ShaderStageFlags::ALL,
+    offset: 0,
+    size: 128
+};
+
+const CHECKER_TEXTURE: &[u8] = include_bytes!("../../assets/checker.png");
+
+/// Scene time a single step advances while paused, in seconds
+const STEP_DURATION: f64 = 1.0 / 60.0;
+/// Scene seconds scrubbed per second of holding the arrow keys, ten times faster with shift
+const SCRUB_SPEED: f64 = 1.0;
+
+const MIN_SPEED: f32 = 1.0 / 16.0;
+const MAX_SPEED: f32 = 16.0;
+
+/// Scene time, which unlike the wall clock in `FrameContext` can be paused, stepped, rewound, sped up and scrubbed.
+/// Updated by `Base::render_loop` with these keys:
+/// - Space pauses and resumes
+/// - Period advances a single step while paused
+/// - Home rewinds to zero
+/// - Plus and minus double and halve the speed, 0 resets it
+/// - Left and right arrows scrub backwards and forwards, faster with shift
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+    /// In seconds, never negative
+    pub time: f64,
+    /// Change of `time` since the previous frame, negative while scrubbing backwards
+    pub delta_time: f32,
+    /// Frames in which `time` moved since the last rewind
+    pub frame: u64,
+    pub speed: f32,
+    pub paused: bool
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock {
+            time: 0.0,
+            delta_time: 0.0,
+            frame: 0,
+            speed: 1.0,
+            paused: false
+        }
+    }
+}
+
+impl Clock {
+    pub(crate) fn update(&mut self, input: &InputState, real_delta_time: Duration) {
+        let real_delta_time = real_delta_time.as_secs_f64();
+
+        // Typing in the UI doesn't control playback
+        let keyboard = !input.ui_wants_keyboard;
+        let pressed = |key: VirtualKeyCode| keyboard && input.key_pressed(key);
+
+        if pressed(VirtualKeyCode::Space) {
+            self.paused = !self.paused;
+        }
+
+        if pressed(VirtualKeyCode::Home) {
+            self.time = 0.0;
+            self.frame = 0;
+        }
+
+        if pressed(VirtualKeyCode::Equals) || pressed(VirtualKeyCode::NumpadAdd) {
+            self.speed = (self.speed * 2.0).min(MAX_SPEED);
+        }
+
+        if pressed(VirtualKeyCode::Minus) || pressed(VirtualKeyCode::NumpadSubtract) {
+            self.speed = (self.speed / 2.0).max(MIN_SPEED);
+        }
+
+        if pressed(VirtualKeyCode::Key0) || pressed(VirtualKeyCode::Numpad0) {
+            self.speed = 1.0;
+        }
+
+        let mut delta_time = 0.0;
+
+        if !self.paused {
+            delta_time += real_delta_time * self.speed as f64;
+        } else if pressed(VirtualKeyCode::Period) {
+            delta_time += STEP_DURATION * self.speed as f64;
+        }
+
+        let key = |key: VirtualKeyCode| if keyboard && input.key_down(key) { 1.0 } else { 0.0 };
+        let boost = if input.modifiers.shift() { 10.0 } else { 1.0 };
+        delta_time += (key(VirtualKeyCode::Right) - key(VirtualKeyCode::Left)) * SCRUB_SPEED * boost * real_delta_time;
+
+        let previous = self.time;
+        self.time = (self.time + delta_time).max(0.0);
+        self.delta_time = (self.time - previous) as f32;
+
+        if self.time != previous {
+            self.frame += 1;
+        }
+    }
+}
+
+/// Bound by the renderer at `GLOBALS_SET` and `GLOBALS_BINDING` for every draw, see `shaders/globals.glsl`.
+/// Laid out for std140, hence the vectors first.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct GlobalsUniform {
+    /// Width and height in pixels, then their reciprocals
+    pub resolution: glm::Vec4,
+    /// Last cursor position in pixels from the top-left corner, then 1.0 while the left and right buttons are held
+    pub mouse: glm::Vec4,
+    /// Year, month (1 to 12), day (1 to 31) and seconds since midnight, in UTC
+    pub date: glm::Vec4,
+    /// Scene time in seconds, see `Clock`
+    pub time: f32,
+    pub delta_time: f32,
+    pub frame: u32,
+    pub speed: f32,
+    /// `DebugViewMode` as an integer
+    pub debug_view: u32,
+    /// 1 when `Settings::reversed_z` is set
+    pub reversed_z: u32
+}
+
+impl Default for GlobalsUniform {
+    fn default() -> Self {
+        GlobalsUniform {
+            resolution: glm::Vec4::zeros(),
+            mouse: glm::Vec4::zeros(),
+            date: glm::Vec4::zeros(),
+            time: 0.0,
+            delta_time: 0.0,
+            frame: 0,
+            speed: 1.0,
+            debug_view: 0,
+            reversed_z: 0
+        }
+    }
+}
+
+/// Host visible uniform buffer holding `GlobalsUniform`, with its descriptor set
+pub struct Globals {
+    pub uniform: GlobalsUniform,
+
+    pub set_layout: vk::DescriptorSetLayout,
+    /// `set_layout` and `GLOBALS_PUSH_CONSTANT_RANGE`, app pipeline layouts starting with the same set and using the
+    /// same range are compatible with it, so the set bound by the renderer stays bound for them
+    pub pipeline_layout: vk::PipelineLayout,
+
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    buffer: Buffer,
+    checker: Texture2D
+}
+
+impl Globals {
+    pub unsafe fn new(base: &Base) -> Self {
+        let buffer = Buffer::new(
+            base,
+            std::mem::size_of::<GlobalsUniform>() as u64,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::SharingMode::EXCLUSIVE,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            true
+        );
+
+        let checker = image::load_from_memory(CHECKER_TEXTURE).expect("Failed to decode checker texture").to_rgba8();
+        let checker = Texture2D::from_rgba8(base, checker.width(), checker.height(), checker.as_raw(), vk::Format::R8G8B8A8_SRGB)
+            .expect("Failed to create checker texture");
+
+        let layout_bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: GLOBALS_BINDING,
+                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::ALL,
+                ..Default::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: GLOBALS_CHECKER_BINDING,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            }
+        ];
+
+        let set_layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&layout_bindings).build();
+        let set_layout = base.device.create_descriptor_set_layout(&set_layout_info, None).unwrap();
+
+        let set_layouts = [set_layout];
+        let push_constant_ranges = [GLOBALS_PUSH_CONSTANT_RANGE];
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(&push_constant_ranges)
+            .build();
+        let pipeline_layout = base.device.create_pipeline_layout(&pipeline_layout_info, None).unwrap();
+
+        let pool_sizes = [
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: 1
+            },
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1
+            }
+        ];
+
+        let descriptor_pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(&pool_sizes)
+            .max_sets(1)
+            .build();
+        let descriptor_pool = base.device.create_descriptor_pool(&descriptor_pool_info, None).unwrap();
+
+        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&set_layouts)
+            .build();
+        let descriptor_set = base.device.allocate_descriptor_sets(&allocate_info).unwrap()[0];
+
+        let buffer_info = vk::DescriptorBufferInfo {
+            buffer: buffer.buffer,
+            offset: 0,
+            range: std::mem::size_of::<GlobalsUniform>() as u64
+        };
+
+        let checker_info = vk::DescriptorImageInfo {
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            image_view: checker.data.view,
+            sampler: checker.data.sampler.unwrap()
+        };
+
+        let writes = [
+            vk::WriteDescriptorSet {
+                dst_set: descriptor_set,
+                dst_binding: GLOBALS_BINDING,
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                p_buffer_info: &buffer_info,
+                ..Default::default()
+            },
+            vk::WriteDescriptorSet {
+                dst_set: descriptor_set,
+                dst_binding: GLOBALS_CHECKER_BINDING,
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                p_image_info: &checker_info,
+                ..Default::default()
+            }
+        ];
+        base.device.update_descriptor_sets(&writes, &[]);
+
+        Globals {
+            uniform: GlobalsUniform {
+                reversed_z: base.settings.reversed_z as u32,
+                ..Default::default()
+            },
+
+            set_layout,
+            pipeline_layout,
+
+            descriptor_pool,
+            descriptor_set,
+            buffer,
+            checker
+        }
+    }
+
+    /// Fills `uniform` from the frame, it reaches the GPU when the next frame is recorded
+    pub fn update(&mut self, frame: &FrameContext) {
+        let (width, height) = (frame.window_size.width as f32, frame.window_size.height as f32);
+        self.uniform.resolution = glm::vec4(width, height, 1.0 / width.max(1.0), 1.0 / height.max(1.0));
+
+        let input = frame.input;
+        let button = |button: MouseButton| if input.mouse_down(button) { 1.0 } else { 0.0 };
+        let cursor = input.cursor_position.unwrap_or(self.uniform.mouse.xy());
+        self.uniform.mouse = glm::vec4(cursor.x, cursor.y, button(MouseButton::Left), button(MouseButton::Right));
+
+        self.uniform.date = utc_date(SystemTime::now());
+
+        self.uniform.time = frame.clock.time as f32;
+        self.uniform.delta_time = frame.clock.delta_time;
+        self.uniform.frame = frame.clock.frame as u32;
+        self.uniform.speed = frame.clock.speed;
+        self.uniform.debug_view = frame.debug_view.mode as u32;
+    }
+
+    /// `GLOBALS_PUSH_CONSTANT_RANGE`, after checking the push constants of `reflections` fit in it.
+    /// Pipelines needing more can't share the globals set, they have to bind their own data instead.
+    pub fn push_constant_range(reflections: &[&ShaderReflection]) -> Result<vk::PushConstantRange, Box<dyn Error>> {
+        for range in reflections.iter().filter_map(|reflection| reflection.push_constant_range()) {
+            if range.offset + range.size > GLOBALS_PUSH_CONSTANT_RANGE.size {
+                return Err(format!(
+                    "Push constants up to byte {} don't fit in the {} shared by pipelines using the globals",
+                    range.offset + range.size,
+                    GLOBALS_PUSH_CONSTANT_RANGE.size
+                ).into());
+            }
+        }
+
+        Ok(GLOBALS_PUSH_CONSTANT_RANGE)
+    }
+
+    /// Uploads `uniform` and binds the set for both graphics and compute pipelines,
+    /// the command buffer fence must have been waited on
+    pub(crate) unsafe fn bind(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        *(self.buffer.ptr.unwrap() as *mut GlobalsUniform) = self.uniform;
+
+        for bind_point in [vk::PipelineBindPoint::GRAPHICS, vk::PipelineBindPoint::COMPUTE] {
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                bind_point,
+                self.pipeline_layout,
+                GLOBALS_SET,
+                &[self.descriptor_set],
+                &[]
+            );
+        }
+    }
+
+    pub unsafe fn destroy(&self, base: &Base) {
+        base.device.destroy_descriptor_pool(self.descriptor_pool, None);
+        base.device.destroy_pipeline_layout(self.pipeline_layout, None);
+        base.device.destroy_descriptor_set_layout(self.set_layout, None);
+        self.buffer.destroy(base);
+        self.checker.data.destroy(base);
+    }
+}
+
+/// (year, month, day, seconds since midnight), days are converted with the proleptic Gregorian calendar
+fn utc_date(time: SystemTime) -> glm::Vec4 {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    let days = (seconds / 86400.0).floor() as i64;
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    glm::vec4(year as f32, month as f32, day as f32, (seconds - days as f64 * 86400.0) as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(seconds: u64) -> glm::Vec4 {
+        utc_date(UNIX_EPOCH + Duration::from_secs(seconds))
+    }
+
+    #[test]
+    fn utc_dates() {
+        assert_eq!(date(0), glm::vec4(1970.0, 1.0, 1.0, 0.0));
+        assert_eq!(date(946_598_400 + 86_399), glm::vec4(1999.0, 12.0, 31.0, 86_399.0));
+        assert_eq!(date(946_684_800), glm::vec4(2000.0, 1.0, 1.0, 0.0));
+        // Leap day of a year divisible by 400, then the first day after February of one divisible by 100 only
+        assert_eq!(date(951_782_400 + 3_600), glm::vec4(2000.0, 2.0, 29.0, 3_600.0));
+        assert_eq!(date(4_107_542_400 - 1), glm::vec4(2100.0, 2.0, 28.0, 86_399.0));
+        assert_eq!(date(4_107_542_400), glm::vec4(2100.0, 3.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn push_constant_ranges() {
+        let reflection = |offset: u32, size: u32| ShaderReflection {
+            stage: vk::ShaderStageFlags::VERTEX,
+            push_constants: Some(PushConstantBlock { name: String::new(), offset, size, members: vec![] }),
+            ..Default::default()
+        };
+
+        let (fits, too_large) = (reflection(64, 64), reflection(64, 68));
+
+        assert_eq!(Globals::push_constant_range(&[]).unwrap().size, GLOBALS_PUSH_CONSTANT_RANGE.size);
+        assert_eq!(Globals::push_constant_range(&[&fits, &ShaderReflection::default()]).unwrap().size, GLOBALS_PUSH_CONSTANT_RANGE.size);
+        assert!(Globals::push_constant_range(&[&fits, &too_large]).is_err());
+    }
+
+    #[test]
+    fn before_epoch() {
+        // Clocks set before 1970 read as the epoch instead of panicking
+        assert_eq!(utc_date(UNIX_EPOCH - Duration::from_secs(10)), glm::vec4(1970.0, 1.0, 1.0, 0.0));
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ash::vk;
+use nalgebra_glm as glm;
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+use crate::{Clock, DebugView};
+
+/// Pixels of scrolling counted as one wheel notch on touchpads
+const PIXELS_PER_LINE: f32 = 40.0;
+
+/// Keyboard and mouse state as of the current frame, built from the window events by `Base::render_loop`.
+/// "Pressed" and "released" only hold for the frame in which the change happened.
+#[derive(Clone, Debug)]
+pub struct InputState {
+    keys_down: HashSet<VirtualKeyCode>,
+    keys_pressed: HashSet<VirtualKeyCode>,
+    keys_released: HashSet<VirtualKeyCode>,
+
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+
+    /// In physical pixels from the top-left corner, `None` while outside the window
+    pub cursor_position: Option<glm::Vec2>,
+    pub cursor_delta: glm::Vec2,
+    /// In wheel notches, positive when scrolling up or right
+    pub scroll_delta: glm::Vec2,
+
+    pub modifiers: ModifiersState,
+    pub focused: bool,
+    pub dropped_files: Vec<PathBuf>,
+
+    /// The UI overlay is under the cursor or being dragged, mouse input should be left to it
+    pub ui_wants_pointer: bool,
+    /// A UI text field has focus, key presses should be left to it
+    pub ui_wants_keyboard: bool
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        InputState {
+            keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            keys_released: HashSet::new(),
+
+            buttons_down: HashSet::new(),
+            buttons_pressed: HashSet::new(),
+            buttons_released: HashSet::new(),
+
+            cursor_position: None,
+            cursor_delta: glm::Vec2::zeros(),
+            scroll_delta: glm::Vec2::zeros(),
+
+            modifiers: ModifiersState::empty(),
+            focused: true,
+            dropped_files: vec![],
+
+            ui_wants_pointer: false,
+            ui_wants_keyboard: false
+        }
+    }
+}
+
+impl InputState {
+    pub fn key_down(&self, key: VirtualKeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn key_released(&self, key: VirtualKeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn mouse_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn mouse_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn mouse_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    pub(crate) fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode: Some(key), .. }, .. } => {
+                match state {
+                    // Key repeats don't count as new presses
+                    ElementState::Pressed => if self.keys_down.insert(*key) {
+                        self.keys_pressed.insert(*key);
+                    },
+                    ElementState::Released => if self.keys_down.remove(key) {
+                        self.keys_released.insert(*key);
+                    }
+                }
+            },
+            WindowEvent::MouseInput { state, button, .. } => {
+                match state {
+                    ElementState::Pressed => if self.buttons_down.insert(*button) {
+                        self.buttons_pressed.insert(*button);
+                    },
+                    ElementState::Released => if self.buttons_down.remove(button) {
+                        self.buttons_released.insert(*button);
+                    }
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = glm::vec2(position.x as f32, position.y as f32);
+
+                if let Some(previous) = self.cursor_position {
+                    self.cursor_delta += position - previous;
+                }
+
+                self.cursor_position = Some(position);
+            },
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll_delta += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => glm::vec2(*x, *y),
+                    MouseScrollDelta::PixelDelta(position) => glm::vec2(position.x as f32, position.y as f32) / PIXELS_PER_LINE
+                };
+            },
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            WindowEvent::Focused(focused) => {
+                self.focused = *focused;
+
+                // Releases happening in another window never reach us
+                if !focused {
+                    self.keys_released.extend(self.keys_down.drain());
+                    self.buttons_released.extend(self.buttons_down.drain());
+                }
+            },
+            WindowEvent::DroppedFile(path) => self.dropped_files.push(path.clone()),
+            _ => ()
+        }
+    }
+
+    /// Forgets everything that only lasts one frame
+    pub(crate) fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+
+        self.cursor_delta = glm::Vec2::zeros();
+        self.scroll_delta = glm::Vec2::zeros();
+        self.dropped_files.clear();
+    }
+}
+
+/// Everything `Base::render_loop` hands to the per-frame callback
+pub struct FrameContext<'a> {
+    pub input: &'a InputState,
+    /// Window events received since the previous frame, in order
+    pub events: &'a [WindowEvent<'static>],
+
+    /// Time since the previous frame, zero on the first one
+    pub delta_time: Duration,
+    /// Time since the render loop started
+    pub time: Duration,
+    pub frame_index: u64,
+    /// Scene time, driven by the playback keys
+    pub clock: &'a Clock,
+    /// Debug visualizations, switched with F2 to F6
+    pub debug_view: &'a DebugView,
+
+    pub window_size: vk::Extent2D
+}
@@ -0,0 +1,301 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use ash::vk;
+use nalgebra_glm as glm;
+
+use crate::*;
+
+/// Material read from the MTL file of an OBJ, texture paths are resolved relative to the OBJ.
+/// `Model::from_gltf` converts glTF materials to it as well.
+#[derive(Clone, Debug)]
+pub struct ObjMaterial {
+    pub name: String,
+
+    pub ambient: glm::Vec3,
+    pub diffuse: glm::Vec3,
+    pub specular: glm::Vec3,
+    pub shininess: f32,
+    /// Opacity, 1.0 is fully opaque
+    pub dissolve: f32,
+    pub optical_density: f32,
+    pub illumination_model: Option<u8>,
+
+    pub ambient_texture: Option<PathBuf>,
+    pub diffuse_texture: Option<PathBuf>,
+    pub specular_texture: Option<PathBuf>,
+    pub normal_texture: Option<PathBuf>,
+    pub shininess_texture: Option<PathBuf>,
+    pub alpha_texture: Option<PathBuf>
+}
+
+impl ObjMaterial {
+    pub(crate) fn from_tobj(material: &tobj::Material, directory: &Path) -> Self {
+        let texture_path = |name: &str| (!name.is_empty()).then(|| directory.join(name));
+
+        ObjMaterial {
+            name: material.name.clone(),
+
+            ambient: material.ambient.into(),
+            diffuse: material.diffuse.into(),
+            specular: material.specular.into(),
+            shininess: material.shininess,
+            dissolve: material.dissolve,
+            optical_density: material.optical_density,
+            illumination_model: material.illumination_model,
+
+            ambient_texture: texture_path(&material.ambient_texture),
+            diffuse_texture: texture_path(&material.diffuse_texture),
+            specular_texture: texture_path(&material.specular_texture),
+            normal_texture: texture_path(&material.normal_texture),
+            shininess_texture: texture_path(&material.shininess_texture),
+            alpha_texture: texture_path(&material.dissolve_texture)
+        }
+    }
+
+    /// Keeps the base color and the textures stored in their own files, metallic-roughness parameters have no MTL equivalent
+    pub(crate) fn from_gltf(material: &gltf::Material, directory: &Path) -> Self {
+        let texture_path = |texture: gltf::Texture| match texture.source().source() {
+            gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => Some(directory.join(uri)),
+            _ => None
+        };
+
+        let pbr = material.pbr_metallic_roughness();
+        let [red, green, blue, alpha] = pbr.base_color_factor();
+
+        ObjMaterial {
+            name: material.name().unwrap_or_default().to_string(),
+
+            ambient: glm::vec3(0.0, 0.0, 0.0),
+            diffuse: glm::vec3(red, green, blue),
+            specular: glm::vec3(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            dissolve: alpha,
+            optical_density: 1.0,
+            illumination_model: None,
+
+            ambient_texture: None,
+            diffuse_texture: pbr.base_color_texture().and_then(|info| texture_path(info.texture())),
+            specular_texture: None,
+            normal_texture: material.normal_texture().and_then(|info| texture_path(info.texture())),
+            shininess_texture: None,
+            alpha_texture: None
+        }
+    }
+
+    pub fn uniform(&self) -> MaterialUniform {
+        MaterialUniform {
+            ambient: glm::vec4(self.ambient.x, self.ambient.y, self.ambient.z, self.optical_density),
+            diffuse: glm::vec4(self.diffuse.x, self.diffuse.y, self.diffuse.z, self.dissolve),
+            specular: glm::vec4(self.specular.x, self.specular.y, self.specular.z, self.shininess)
+        }
+    }
+}
+
+/// Scalar material parameters packed in vec4s, so the layout is the same under std140 and std430
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct MaterialUniform {
+    /// Optical density in w
+    pub ambient: glm::Vec4,
+    /// Dissolve (alpha) in w
+    pub diffuse: glm::Vec4,
+    /// Shininess in w
+    pub specular: glm::Vec4
+}
+
+/// The texture maps of an `ObjMaterial` uploaded to the GPU, `None` where the material has no map. Only the diffuse
+/// map is sampled by the shaders, the other maps stay as paths in `ObjMaterial` for apps doing their own shading.
+pub struct MaterialTextures {
+    pub diffuse: Option<Texture2D>
+}
+
+impl MaterialTextures {
+    pub unsafe fn load(base: &Base, material: &ObjMaterial) -> Result<Self, Box<dyn Error>> {
+        let diffuse = match &material.diffuse_texture {
+            Some(path) => Some(Texture2D::load_from_file(base, &path.to_string_lossy())?),
+            None => None
+        };
+
+        Ok(MaterialTextures { diffuse })
+    }
+
+    pub unsafe fn destroy(&self, base: &Base) {
+        if let Some(texture) = &self.diffuse {
+            texture.data.destroy(base);
+        }
+    }
+}
+
+/// Binding of the `MaterialUniform` in the sets of `MaterialDescriptors`
+pub const MATERIAL_UNIFORM_BINDING: u32 = 0;
+/// Binding of the diffuse map in the sets of `MaterialDescriptors`
+pub const MATERIAL_DIFFUSE_BINDING: u32 = 1;
+
+/// One descriptor set per material of a model, bound for each submesh according to `Submesh::material`.
+/// Every set holds the `MaterialUniform` and the diffuse map, white when the material has none. Submeshes without
+/// material get an extra set with a white material and `default_texture`.
+pub struct MaterialDescriptors {
+    pub set_layout: vk::DescriptorSetLayout,
+    /// Indexed by material, then the one for submeshes without material
+    pub descriptor_sets: Vec<vk::DescriptorSet>,
+    pub textures: Vec<MaterialTextures>,
+
+    descriptor_pool: vk::DescriptorPool,
+    uniform_buffer: Buffer,
+    white: Texture2D
+}
+
+impl MaterialDescriptors {
+    /// Materials whose maps fail to load are logged and drawn without maps
+    pub unsafe fn new(base: &Base, materials: &[ObjMaterial], default_texture: &Texture2D) -> Self {
+        let set_count = materials.len() as u32 + 1;
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: MATERIAL_UNIFORM_BINDING,
+                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: MATERIAL_DIFFUSE_BINDING,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            }
+        ];
+        let set_layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings).build();
+        let set_layout = base.device.create_descriptor_set_layout(&set_layout_info, None).unwrap();
+
+        let pool_sizes = [
+            vk::DescriptorPoolSize { ty: vk::DescriptorType::UNIFORM_BUFFER, descriptor_count: set_count },
+            vk::DescriptorPoolSize { ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER, descriptor_count: set_count }
+        ];
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(&pool_sizes)
+            .max_sets(set_count)
+            .build();
+        let descriptor_pool = base.device.create_descriptor_pool(&pool_info, None).unwrap();
+
+        let set_layouts = vec![set_layout; set_count as usize];
+        let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&set_layouts)
+            .build();
+        let descriptor_sets = base.device.allocate_descriptor_sets(&alloc_info).unwrap();
+
+        // All the uniforms in one buffer, each at an offset the device accepts for descriptors
+        let alignment = base.device_properties.limits.min_uniform_buffer_offset_alignment.max(1);
+        let stride = (std::mem::size_of::<MaterialUniform>() as u64).next_multiple_of(alignment);
+
+        let uniform_buffer = Buffer::new(
+            base,
+            stride * set_count as u64,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::SharingMode::EXCLUSIVE,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            true
+        );
+
+        let white_uniform = MaterialUniform {
+            ambient: glm::vec4(0.0, 0.0, 0.0, 1.0),
+            diffuse: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            specular: glm::vec4(0.0, 0.0, 0.0, 0.0)
+        };
+
+        let uniforms: Vec<MaterialUniform> = materials
+            .iter()
+            .map(|material| material.uniform())
+            .chain(std::iter::once(white_uniform))
+            .collect();
+
+        for (index, uniform) in uniforms.iter().enumerate() {
+            let ptr = uniform_buffer.ptr.unwrap().cast::<u8>().add(index * stride as usize);
+            *(ptr as *mut MaterialUniform) = *uniform;
+        }
+
+        uniform_buffer.unmap_memory(base);
+
+        let white = Texture2D::from_rgba8(base, 1, 1, &[255; 4], vk::Format::R8G8B8A8_UNORM).unwrap();
+
+        let textures: Vec<MaterialTextures> = materials
+            .iter()
+            .map(|material| {
+                MaterialTextures::load(base, material).unwrap_or_else(|error| {
+                    println!("Failed to load the maps of material {}: {error}", material.name);
+                    MaterialTextures { diffuse: None }
+                })
+            })
+            .collect();
+
+        for (index, &descriptor_set) in descriptor_sets.iter().enumerate() {
+            let diffuse = match textures.get(index) {
+                Some(textures) => textures.diffuse.as_ref().unwrap_or(&white),
+                None => default_texture
+            };
+
+            let buffer_info = vk::DescriptorBufferInfo {
+                buffer: uniform_buffer.buffer,
+                offset: stride * index as u64,
+                range: std::mem::size_of::<MaterialUniform>() as u64
+            };
+
+            let image_info = vk::DescriptorImageInfo {
+                image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                image_view: diffuse.data.view,
+                sampler: diffuse.data.sampler.unwrap()
+            };
+
+            let writes = [
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_set,
+                    dst_binding: MATERIAL_UNIFORM_BINDING,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                    p_buffer_info: &buffer_info,
+                    ..Default::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_set,
+                    dst_binding: MATERIAL_DIFFUSE_BINDING,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    p_image_info: &image_info,
+                    ..Default::default()
+                }
+            ];
+            base.device.update_descriptor_sets(&writes, &[]);
+        }
+
+        MaterialDescriptors {
+            set_layout,
+            descriptor_sets,
+            textures,
+
+            descriptor_pool,
+            uniform_buffer,
+            white
+        }
+    }
+
+    /// The set for `Submesh::material`, out of range indices get the default one
+    pub fn descriptor_set(&self, material: Option<usize>) -> vk::DescriptorSet {
+        let default = self.descriptor_sets.len() - 1;
+        self.descriptor_sets[material.filter(|&material| material < default).unwrap_or(default)]
+    }
+
+    pub unsafe fn destroy(&self, base: &Base) {
+        for textures in self.textures.iter() {
+            textures.destroy(base);
+        }
+
+        self.white.data.destroy(base);
+        self.uniform_buffer.destroy(base);
+
+        base.device.destroy_descriptor_pool(self.descriptor_pool, None);
+        base.device.destroy_descriptor_set_layout(self.set_layout, None);
+    }
+}
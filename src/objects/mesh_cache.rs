@@ -0,0 +1,469 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use nalgebra_glm as glm;
+
+use crate::*;
+
+const CACHE_MAGIC: &[u8; 8] = b"TRSRMESH";
+
+/// Bump whenever the layout of the cache or the output of the loaders changes, older caches are then rebuilt
+const CACHE_VERSION: u32 = 2;
+
+const CACHE_EXTENSION: &str = "meshcache";
+
+/// Processing applied by `Model::load` after parsing the file
+#[derive(Clone, Copy, Debug)]
+pub struct ModelLoadOptions {
+    pub optimize: bool,
+    /// See `Model::generate_lods`
+    pub lod_levels: usize,
+    pub lod_ratio: f32,
+    /// Read and write the binary cache next to the source file
+    pub cache: bool
+}
+
+impl Default for ModelLoadOptions {
+    fn default() -> Self {
+        ModelLoadOptions {
+            optimize: false,
+            lod_levels: 0,
+            lod_ratio: 0.5,
+            cache: true
+        }
+    }
+}
+
+impl Model {
+    /// `from_file` followed by the processing in `options`. The result is stored in `<file>.meshcache`, keyed by the
+    /// hash of the file contents and the options, and loaded from there as long as neither changed.
+    /// Files referenced by the source (OBJ materials, glTF buffers) are not part of the key.
+    pub fn load(filepath: &str, options: &ModelLoadOptions) -> Result<Self, Box<dyn Error>> {
+        let source = std::fs::read(filepath)?;
+        let key = cache_key(&source, options);
+        let cache_path = PathBuf::from(format!("{filepath}.{CACHE_EXTENSION}"));
+
+        if options.cache {
+            if let Ok(cache) = std::fs::read(&cache_path) {
+                if let Ok(model) = Model::from_cache(&cache, key) {
+                    return Ok(model);
+                }
+            }
+        }
+
+        let mut model = Model::from_file(filepath)?;
+
+        if options.lod_levels > 0 {
+            model.generate_lods(options.lod_levels, options.lod_ratio);
+        }
+
+        if options.optimize {
+            model.optimize();
+        }
+
+        if options.cache {
+            if let Err(error) = std::fs::write(&cache_path, model.to_cache(key)) {
+                println!("Failed to write mesh cache {}: {error}", cache_path.display());
+            }
+        }
+
+        Ok(model)
+    }
+
+    fn to_cache(&self, key: u64) -> Vec<u8> {
+        let mut writer = CacheWriter(CACHE_MAGIC.to_vec());
+        writer.u32(CACHE_VERSION);
+        writer.u64(key);
+
+        writer.u8(match self.topology {
+            MeshTopology::Triangles => 0,
+            MeshTopology::Points => 1
+        });
+
+        writer.u32(self.vertices.len() as u32);
+        for vertex in self.vertices.iter() {
+            for &component in vertex.components() {
+                writer.f32(component);
+            }
+        }
+
+        writer.indices(&self.indices, &self.submeshes);
+
+        writer.u32(self.lods.len() as u32);
+        for lod in self.lods.iter() {
+            writer.indices(&lod.indices, &lod.submeshes);
+        }
+
+        writer.u32(self.materials.len() as u32);
+        for material in self.materials.iter() {
+            writer.string(&material.name);
+
+            for &component in material.ambient.iter().chain(material.diffuse.iter()).chain(material.specular.iter()) {
+                writer.f32(component);
+            }
+
+            writer.f32(material.shininess);
+            writer.f32(material.dissolve);
+            writer.f32(material.optical_density);
+            writer.u32(material.illumination_model.map_or(u32::MAX, u32::from));
+
+            for texture in material.textures() {
+                writer.string(&texture.as_ref().map_or(String::new(), |path| path.to_string_lossy().into_owned()));
+            }
+        }
+
+        writer.0
+    }
+
+    fn from_cache(data: &[u8], key: u64) -> Result<Self, Box<dyn Error>> {
+        let mut reader = CacheReader { data, offset: 0 };
+
+        if reader.bytes(CACHE_MAGIC.len())? != CACHE_MAGIC || reader.u32()? != CACHE_VERSION || reader.u64()? != key {
+            return Err("Stale mesh cache".into());
+        }
+
+        let topology = match reader.u8()? {
+            0 => MeshTopology::Triangles,
+            1 => MeshTopology::Points,
+            _ => return Err("Unknown topology in mesh cache".into())
+        };
+
+        let vertices = (0..reader.u32()?).map(|_| {
+            let mut components = [0.0; 16];
+            for component in components.iter_mut() {
+                *component = reader.f32()?;
+            }
+
+            let mut vertex = Vertex::new(
+                glm::make_vec3(&components[0..3]),
+                glm::make_vec2(&components[3..5]),
+                glm::make_vec3(&components[5..8]),
+                glm::make_vec4(&components[8..12])
+            );
+            vertex.tangent = glm::make_vec4(&components[12..16]);
+
+            Ok(vertex)
+        }).collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+        let (indices, submeshes) = reader.indices()?;
+
+        let lods = (0..reader.u32()?)
+            .map(|_| reader.indices().map(|(indices, submeshes)| Lod { indices, submeshes }))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let materials = (0..reader.u32()?).map(|_| {
+            let name = reader.string()?;
+            let mut colors = [0.0; 9];
+            for component in colors.iter_mut() {
+                *component = reader.f32()?;
+            }
+
+            let (shininess, dissolve, optical_density) = (reader.f32()?, reader.f32()?, reader.f32()?);
+            let illumination_model = u8::try_from(reader.u32()?).ok();
+
+            let mut texture = || -> Result<Option<PathBuf>, Box<dyn Error>> {
+                let path = reader.string()?;
+                Ok((!path.is_empty()).then(|| PathBuf::from(path)))
+            };
+
+            Ok(ObjMaterial {
+                name,
+                ambient: glm::make_vec3(&colors[0..3]),
+                diffuse: glm::make_vec3(&colors[3..6]),
+                specular: glm::make_vec3(&colors[6..9]),
+                shininess,
+                dissolve,
+                optical_density,
+                illumination_model,
+
+                ambient_texture: texture()?,
+                diffuse_texture: texture()?,
+                specular_texture: texture()?,
+                normal_texture: texture()?,
+                shininess_texture: texture()?,
+                alpha_texture: texture()?
+            })
+        }).collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+        // A corrupted cache must not reach the GPU with out of range indices
+        check_indices(&indices, &submeshes, vertices.len(), materials.len())?;
+        for lod in lods.iter() {
+            check_indices(&lod.indices, &lod.submeshes, vertices.len(), materials.len())?;
+        }
+
+        Ok(Model {
+            vertices,
+            indices,
+            submeshes,
+            materials,
+            topology,
+            lods
+        })
+    }
+}
+
+impl ObjMaterial {
+    /// In the order of the struct fields
+    fn textures(&self) -> [&Option<PathBuf>; 6] {
+        [
+            &self.ambient_texture,
+            &self.diffuse_texture,
+            &self.specular_texture,
+            &self.normal_texture,
+            &self.shininess_texture,
+            &self.alpha_texture
+        ]
+    }
+}
+
+/// Indices within the vertices, submeshes within the indices and materials within the model
+fn check_indices(indices: &[u32], submeshes: &[Submesh], vertex_count: usize, material_count: usize) -> Result<(), Box<dyn Error>> {
+    if indices.iter().any(|&index| index as usize >= vertex_count) {
+        return Err("Vertex index out of range in mesh cache".into());
+    }
+
+    for submesh in submeshes {
+        let end = submesh.first_index as u64 + submesh.index_count as u64;
+
+        if end > indices.len() as u64 {
+            return Err("Submesh out of range in mesh cache".into());
+        }
+
+        if submesh.material.is_some_and(|material| material >= material_count) {
+            return Err("Material index out of range in mesh cache".into());
+        }
+    }
+
+    Ok(())
+}
+
+/// 64 bit FNV-1a over the source file and the options, stable across runs and platforms
+fn cache_key(source: &[u8], options: &ModelLoadOptions) -> u64 {
+    let mut options_bytes = vec![options.optimize as u8];
+    options_bytes.extend((options.lod_levels as u64).to_le_bytes());
+    options_bytes.extend(options.lod_ratio.to_le_bytes());
+
+    source.iter().chain(options_bytes.iter()).fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+struct CacheWriter(Vec<u8>);
+
+impl CacheWriter {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.0.extend(value.as_bytes());
+    }
+
+    fn indices(&mut self, indices: &[u32], submeshes: &[Submesh]) {
+        self.u32(indices.len() as u32);
+        for &index in indices {
+            self.u32(index);
+        }
+
+        self.u32(submeshes.len() as u32);
+        for submesh in submeshes {
+            self.u32(submesh.first_index);
+            self.u32(submesh.index_count);
+            self.u32(submesh.material.map_or(u32::MAX, |material| material as u32));
+        }
+    }
+}
+
+struct CacheReader<'a> {
+    data: &'a [u8],
+    offset: usize
+}
+
+impl<'a> CacheReader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let bytes = self.data
+            .get(self.offset..self.offset + count)
+            .ok_or("Truncated mesh cache")?;
+
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Box<dyn Error>> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, Box<dyn Error>> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, Box<dyn Error>> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<String, Box<dyn Error>> {
+        let length = self.u32()? as usize;
+        Ok(String::from_utf8(self.bytes(length)?.to_vec())?)
+    }
+
+    fn indices(&mut self) -> Result<(Vec<u32>, Vec<Submesh>), Box<dyn Error>> {
+        let indices = (0..self.u32()?).map(|_| self.u32()).collect::<Result<Vec<_>, _>>()?;
+
+        let submeshes = (0..self.u32()?).map(|_| {
+            Ok(Submesh {
+                first_index: self.u32()?,
+                index_count: self.u32()?,
+                material: Some(self.u32()?).filter(|&material| material != u32::MAX).map(|material| material as usize)
+            })
+        }).collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+        Ok((indices, submeshes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material(name: &str) -> ObjMaterial {
+        ObjMaterial {
+            name: name.to_string(),
+
+            ambient: glm::vec3(0.1, 0.2, 0.3),
+            diffuse: glm::vec3(0.4, 0.5, 0.6),
+            specular: glm::vec3(0.7, 0.8, 0.9),
+            shininess: 32.0,
+            dissolve: 0.5,
+            optical_density: 1.45,
+            illumination_model: Some(2),
+
+            ambient_texture: None,
+            diffuse_texture: Some(PathBuf::from("textures/diffuse.png")),
+            specular_texture: None,
+            normal_texture: Some(PathBuf::from("textures/normal.png")),
+            shininess_texture: None,
+            alpha_texture: None
+        }
+    }
+
+    fn model() -> Model {
+        let mut model = Model::cube(1.0, 2);
+
+        let half = model.indices.len() as u32 / 2;
+        model.submeshes = vec![
+            Submesh { first_index: 0, index_count: half, material: Some(1) },
+            Submesh { first_index: half, index_count: model.indices.len() as u32 - half, material: None }
+        ];
+        model.materials = vec![material("first"), material("second")];
+        model.generate_lods(2, 0.5);
+
+        model
+    }
+
+    #[test]
+    fn round_trip() {
+        let model = model();
+        let cached = Model::from_cache(&model.to_cache(42), 42).unwrap();
+
+        assert_eq!(cached.topology, model.topology);
+        assert_eq!(cached.vertices, model.vertices);
+        assert_eq!(cached.indices, model.indices);
+        assert_eq!(cached.submeshes.len(), model.submeshes.len());
+
+        for (cached, submesh) in cached.submeshes.iter().zip(model.submeshes.iter()) {
+            assert_eq!((cached.first_index, cached.index_count, cached.material), (submesh.first_index, submesh.index_count, submesh.material));
+        }
+
+        assert_eq!(cached.lods.len(), model.lods.len());
+        for (cached, lod) in cached.lods.iter().zip(model.lods.iter()) {
+            assert_eq!(cached.indices, lod.indices);
+            assert_eq!(cached.submeshes.len(), lod.submeshes.len());
+        }
+
+        assert_eq!(cached.materials.len(), 2);
+        let (cached, material) = (&cached.materials[1], &model.materials[1]);
+        assert_eq!(cached.name, material.name);
+        assert_eq!((cached.ambient, cached.diffuse, cached.specular), (material.ambient, material.diffuse, material.specular));
+        assert_eq!((cached.shininess, cached.dissolve, cached.optical_density), (material.shininess, material.dissolve, material.optical_density));
+        assert_eq!(cached.illumination_model, material.illumination_model);
+        assert_eq!(cached.textures(), material.textures());
+    }
+
+    #[test]
+    fn point_cloud_round_trip() {
+        let mut model = Model::new(Model::plane(1.0, 1.0, 1).vertices, vec![0, 1, 2, 3]);
+        model.topology = MeshTopology::Points;
+
+        let cached = Model::from_cache(&model.to_cache(7), 7).unwrap();
+        assert_eq!(cached.topology, MeshTopology::Points);
+        assert_eq!(cached.indices, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn stale_or_truncated() {
+        let data = model().to_cache(42);
+
+        assert!(Model::from_cache(&data, 43).is_err());
+        assert!(Model::from_cache(&data[..data.len() - 1], 42).is_err());
+        assert!(Model::from_cache(&data[..CACHE_MAGIC.len()], 42).is_err());
+
+        let mut version = data.clone();
+        version[CACHE_MAGIC.len()..CACHE_MAGIC.len() + 4].copy_from_slice(&(CACHE_VERSION + 1).to_le_bytes());
+        assert!(Model::from_cache(&version, 42).is_err());
+    }
+
+    #[test]
+    fn out_of_range() {
+        let vertices = Model::plane(1.0, 1.0, 1).vertices;
+
+        let mut index = Model::new(vertices.clone(), vec![0, 1, 2]);
+        index.indices[2] = vertices.len() as u32;
+        assert!(Model::from_cache(&index.to_cache(1), 1).is_err());
+
+        let mut submesh = Model::new(vertices.clone(), vec![0, 1, 2]);
+        submesh.submeshes[0].index_count = 4;
+        assert!(Model::from_cache(&submesh.to_cache(1), 1).is_err());
+
+        let mut material = Model::new(vertices.clone(), vec![0, 1, 2]);
+        material.submeshes[0].material = Some(0);
+        assert!(Model::from_cache(&material.to_cache(1), 1).is_err());
+
+        let mut lod = Model::new(vertices, vec![0, 1, 2]);
+        lod.lods = vec![Lod { indices: vec![0, 1, 2], submeshes: vec![Submesh { first_index: 3, index_count: 3, material: None }] }];
+        assert!(Model::from_cache(&lod.to_cache(1), 1).is_err());
+    }
+
+    #[test]
+    fn key() {
+        let options = ModelLoadOptions::default();
+        let key = cache_key(b"v 0 0 0", &options);
+
+        assert_eq!(key, cache_key(b"v 0 0 0", &options));
+        assert_ne!(key, cache_key(b"v 0 0 1", &options));
+        assert_ne!(key, cache_key(b"v 0 0 0", &ModelLoadOptions { optimize: true, ..options }));
+        assert_ne!(key, cache_key(b"v 0 0 0", &ModelLoadOptions { lod_levels: 1, ..options }));
+        assert_ne!(key, cache_key(b"v 0 0 0", &ModelLoadOptions { lod_ratio: 0.25, ..options }));
+        // Whether the cache is used doesn't change its content
+        assert_eq!(key, cache_key(b"v 0 0 0", &ModelLoadOptions { cache: false, ..options }));
+
+        // Caches written on another platform or by another build stay valid
+        assert_eq!(key, 0x932e_7656_0585_9f9a);
+    }
+}
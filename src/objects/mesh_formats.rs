@@ -0,0 +1,452 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+
+use nalgebra_glm as glm;
+
+use crate::*;
+
+impl Model {
+    /// Loads an ASCII or binary PLY file. Vertex colors are kept, missing normals are smoothed from the faces
+    /// and files without faces become point clouds drawn with `MeshTopology::Points`.
+    pub fn from_ply(filepath: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_ply_data(&std::fs::read(filepath)?)
+    }
+
+    fn from_ply_data(data: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let (elements, format, body_start) = parse_ply_header(data)?;
+
+        let mut body = match format {
+            PlyFormat::Ascii => PlyBody::Ascii(std::str::from_utf8(&data[body_start..])?.split_ascii_whitespace()),
+            PlyFormat::BinaryLittleEndian => PlyBody::Binary { data: &data[body_start..], offset: 0, big_endian: false },
+            PlyFormat::BinaryBigEndian => PlyBody::Binary { data: &data[body_start..], offset: 0, big_endian: true }
+        };
+
+        let mut vertices = vec![];
+        let mut faces: Vec<Vec<u32>> = vec![];
+
+        let mut has_normals = false;
+
+        for element in elements.iter() {
+            match element.name.as_str() {
+                "vertex" => {
+                    let find = |names: &[&str]| element.properties
+                        .iter()
+                        .position(|property| names.contains(&property.name.as_str()));
+
+                    let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+                    let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+                    let uv = [find(&["u", "s", "texture_u"]), find(&["v", "t", "texture_v"])];
+                    let color = [find(&["red", "r"]), find(&["green", "g"]), find(&["blue", "b"]), find(&["alpha", "a"])];
+
+                    has_normals = normal.iter().all(Option::is_some);
+
+                    for _ in 0..element.count {
+                        let row = body.read_row(&element.properties)?;
+                        let value = |index: Option<usize>, default: f32| index.map_or(default, |index| row[index] as f32);
+
+                        // Integer colors are normalized by the maximum value of their type
+                        let color_value = |index: Option<usize>| index.map_or(1.0, |index| {
+                            match element.properties[index].kind {
+                                PlyPropertyKind::Scalar(PlyScalar::Uint8) => row[index] as f32 / 255.0,
+                                PlyPropertyKind::Scalar(PlyScalar::Uint16) => row[index] as f32 / 65535.0,
+                                _ => row[index] as f32
+                            }
+                        });
+
+                        vertices.push(Vertex::new(
+                            glm::vec3(value(position[0], 0.0), value(position[1], 0.0), value(position[2], 0.0)),
+                            glm::vec2(value(uv[0], 0.0), 1.0 - value(uv[1], 1.0)),
+                            glm::vec3(value(normal[0], 0.0), value(normal[1], 0.0), value(normal[2], 1.0)),
+                            glm::vec4(color_value(color[0]), color_value(color[1]), color_value(color[2]), color_value(color[3]))
+                        ));
+                    }
+                },
+                "face" => {
+                    let list = element.properties
+                        .iter()
+                        .position(|property| property.name == "vertex_indices" || property.name == "vertex_index")
+                        .ok_or("PLY face element without vertex indices")?;
+
+                    for _ in 0..element.count {
+                        let lists = body.read_lists(&element.properties)?;
+                        faces.push(lists[list].iter().map(|&index| index as u32).collect());
+                    }
+                },
+                _ => {
+                    for _ in 0..element.count {
+                        body.read_lists(&element.properties)?;
+                    }
+                }
+            }
+        }
+
+        if faces.is_empty() {
+            let indices = (0..vertices.len() as u32).collect();
+
+            let mut model = Model::new(vertices, indices);
+            model.topology = MeshTopology::Points;
+            return Ok(model);
+        }
+
+        // Polygons are triangulated as fans
+        let mut indices = vec![];
+        for face in faces.iter() {
+            for corner in 1..face.len().saturating_sub(1) {
+                indices.extend([face[0], face[corner], face[corner + 1]]);
+            }
+        }
+
+        if let Some(&index) = indices.iter().find(|&&index| index as usize >= vertices.len()) {
+            return Err(format!("PLY face references missing vertex {index}").into());
+        }
+
+        let mut model = Model::new(vertices, indices);
+
+        if !has_normals {
+            model.compute_normals();
+        }
+
+        model.compute_tangents();
+        Ok(model)
+    }
+
+    /// Loads an ASCII or binary STL file. Stored normals are often missing or wrong, so flat normals are computed from the triangles.
+    pub fn from_stl(filepath: &str) -> Result<Self, Box<dyn Error>> {
+        let mut reader = BufReader::new(File::open(filepath)?);
+        let mesh = stl_io::read_stl(&mut reader)?;
+
+        let mut triangles = vec![];
+
+        for face in mesh.faces.iter() {
+            let positions = face.vertices.map(|index| {
+                let vertex = mesh.vertices[index];
+                glm::vec3(vertex[0], vertex[1], vertex[2])
+            });
+
+            let edge_normal = (positions[1] - positions[0]).cross(&(positions[2] - positions[0]));
+            let normal = if edge_normal.norm_squared() > 0.0 { edge_normal.normalize() } else { glm::vec3(0.0, 0.0, 1.0) };
+
+            for position in positions {
+                triangles.push(Vertex::new(position, glm::vec2(0.0, 0.0), normal, glm::vec4(1.0, 1.0, 1.0, 1.0)));
+            }
+        }
+
+        let mut model = Model::from_triangles(&triangles);
+        model.compute_tangents();
+        Ok(model)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PlyScalar {
+    Int8,
+    Uint8,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Float32,
+    Float64
+}
+
+impl PlyScalar {
+    fn parse(name: &str) -> Option<Self> {
+        let scalar = match name {
+            "char" | "int8" => PlyScalar::Int8,
+            "uchar" | "uint8" => PlyScalar::Uint8,
+            "short" | "int16" => PlyScalar::Int16,
+            "ushort" | "uint16" => PlyScalar::Uint16,
+            "int" | "int32" => PlyScalar::Int32,
+            "uint" | "uint32" => PlyScalar::Uint32,
+            "float" | "float32" => PlyScalar::Float32,
+            "double" | "float64" => PlyScalar::Float64,
+            _ => return None
+        };
+
+        Some(scalar)
+    }
+
+    fn size(self) -> usize {
+        match self {
+            PlyScalar::Int8 | PlyScalar::Uint8 => 1,
+            PlyScalar::Int16 | PlyScalar::Uint16 => 2,
+            PlyScalar::Int32 | PlyScalar::Uint32 | PlyScalar::Float32 => 4,
+            PlyScalar::Float64 => 8
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PlyPropertyKind {
+    Scalar(PlyScalar),
+    List { count: PlyScalar, item: PlyScalar }
+}
+
+#[derive(Clone, Debug)]
+struct PlyProperty {
+    name: String,
+    kind: PlyPropertyKind
+}
+
+#[derive(Clone, Debug)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>
+}
+
+/// Returns the declared elements, the body format and where the body starts
+fn parse_ply_header(data: &[u8]) -> Result<(Vec<PlyElement>, PlyFormat, usize), Box<dyn Error>> {
+    let end_header = data
+        .windows(b"end_header".len())
+        .position(|window| window == b"end_header")
+        .ok_or("PLY file without end_header")?;
+
+    let body_start = data[end_header..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(data.len(), |newline| end_header + newline + 1);
+
+    let header = std::str::from_utf8(&data[..end_header])?;
+    let mut lines = header.lines().map(str::trim);
+
+    if lines.next() != Some("ply") {
+        return Err("Not a PLY file".into());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = vec![];
+
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["format", "ascii", ..] => format = Some(PlyFormat::Ascii),
+            ["format", "binary_little_endian", ..] => format = Some(PlyFormat::BinaryLittleEndian),
+            ["format", "binary_big_endian", ..] => format = Some(PlyFormat::BinaryBigEndian),
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count.parse()?,
+                properties: vec![]
+            }),
+            ["property", "list", count, item, name] => {
+                let kind = PlyPropertyKind::List {
+                    count: PlyScalar::parse(count).ok_or_else(|| format!("Unknown PLY type {count}"))?,
+                    item: PlyScalar::parse(item).ok_or_else(|| format!("Unknown PLY type {item}"))?
+                };
+
+                elements
+                    .last_mut()
+                    .ok_or("PLY property declared before any element")?
+                    .properties
+                    .push(PlyProperty { name: name.to_string(), kind });
+            },
+            ["property", scalar, name] => {
+                let kind = PlyPropertyKind::Scalar(PlyScalar::parse(scalar).ok_or_else(|| format!("Unknown PLY type {scalar}"))?);
+
+                elements
+                    .last_mut()
+                    .ok_or("PLY property declared before any element")?
+                    .properties
+                    .push(PlyProperty { name: name.to_string(), kind });
+            },
+            _ => () // comment, obj_info
+        }
+    }
+
+    Ok((elements, format.ok_or("PLY file without format")?, body_start))
+}
+
+enum PlyBody<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary { data: &'a [u8], offset: usize, big_endian: bool }
+}
+
+impl PlyBody<'_> {
+    fn read(&mut self, scalar: PlyScalar) -> Result<f64, Box<dyn Error>> {
+        match self {
+            PlyBody::Ascii(words) => Ok(words.next().ok_or("Unexpected end of PLY data")?.parse()?),
+            PlyBody::Binary { data, offset, big_endian } => {
+                let size = scalar.size();
+                let mut bytes = data
+                    .get(*offset..*offset + size)
+                    .ok_or("Unexpected end of PLY data")?
+                    .to_vec();
+                *offset += size;
+
+                if *big_endian {
+                    bytes.reverse();
+                }
+
+                let value = match scalar {
+                    PlyScalar::Int8 => bytes[0] as i8 as f64,
+                    PlyScalar::Uint8 => bytes[0] as f64,
+                    PlyScalar::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    PlyScalar::Uint16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    PlyScalar::Int32 => i32::from_le_bytes(bytes[..4].try_into()?) as f64,
+                    PlyScalar::Uint32 => u32::from_le_bytes(bytes[..4].try_into()?) as f64,
+                    PlyScalar::Float32 => f32::from_le_bytes(bytes[..4].try_into()?) as f64,
+                    PlyScalar::Float64 => f64::from_le_bytes(bytes[..8].try_into()?)
+                };
+
+                Ok(value)
+            }
+        }
+    }
+
+    /// Reads one element instance, list properties are skipped and read as `NaN`
+    fn read_row(&mut self, properties: &[PlyProperty]) -> Result<Vec<f64>, Box<dyn Error>> {
+        let mut row = Vec::with_capacity(properties.len());
+
+        for property in properties {
+            match property.kind {
+                PlyPropertyKind::Scalar(scalar) => row.push(self.read(scalar)?),
+                PlyPropertyKind::List { count, item } => {
+                    for _ in 0..self.read(count)? as usize {
+                        self.read(item)?;
+                    }
+
+                    row.push(f64::NAN);
+                }
+            }
+        }
+
+        Ok(row)
+    }
+
+    /// Reads one element instance, scalar properties become single item lists
+    fn read_lists(&mut self, properties: &[PlyProperty]) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+        let mut lists = Vec::with_capacity(properties.len());
+
+        for property in properties {
+            match property.kind {
+                PlyPropertyKind::Scalar(scalar) => lists.push(vec![self.read(scalar)?]),
+                PlyPropertyKind::List { count, item } => {
+                    let count = self.read(count)? as usize;
+                    lists.push((0..count).map(|_| self.read(item)).collect::<Result<_, _>>()?);
+                }
+            }
+        }
+
+        Ok(lists)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD_HEADER: &str = "element vertex 4\n\
+        property float x\n\
+        property float y\n\
+        property float z\n\
+        property uchar red\n\
+        property uchar green\n\
+        property uchar blue\n\
+        element face 1\n\
+        property list uchar int vertex_indices\n\
+        end_header\n";
+
+    const QUAD_POSITIONS: [[f32; 3]; 4] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+
+    fn binary_quad(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian { "binary_big_endian" } else { "binary_little_endian" };
+        let mut data = format!("ply\nformat {format} 1.0\n{QUAD_HEADER}").into_bytes();
+
+        for position in QUAD_POSITIONS {
+            for component in position {
+                data.extend(if big_endian { component.to_be_bytes() } else { component.to_le_bytes() });
+            }
+
+            data.extend([255, 0, 51]);
+        }
+
+        data.push(4);
+        for index in 0..4i32 {
+            data.extend(if big_endian { index.to_be_bytes() } else { index.to_le_bytes() });
+        }
+
+        data
+    }
+
+    fn assert_quad(model: &Model) {
+        assert_eq!(model.topology, MeshTopology::Triangles);
+        assert_eq!(model.indices, [0, 1, 2, 0, 2, 3]);
+
+        for (vertex, position) in model.vertices.iter().zip(QUAD_POSITIONS) {
+            assert_eq!(vertex.pos, glm::Vec3::from(position));
+            assert_eq!(vertex.color, glm::vec4(1.0, 0.0, 0.2, 1.0));
+            // No normals in the file, smoothed from the counter-clockwise face
+            assert!((vertex.normal - glm::vec3(0.0, 0.0, 1.0)).norm() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn ascii_quad() {
+        let data = format!("ply\nformat ascii 1.0\ncomment made by hand\n{QUAD_HEADER}\
+            0 0 0 255 0 51\n1 0 0 255 0 51\n1 1 0 255 0 51\n0 1 0 255 0 51\n\
+            4 0 1 2 3\n");
+
+        assert_quad(&Model::from_ply_data(data.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn binary_little_endian_quad() {
+        assert_quad(&Model::from_ply_data(&binary_quad(false)).unwrap());
+    }
+
+    #[test]
+    fn binary_big_endian_quad() {
+        assert_quad(&Model::from_ply_data(&binary_quad(true)).unwrap());
+    }
+
+    #[test]
+    fn point_cloud() {
+        let data = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nend_header\n\
+            0 0 0\n1 2 3\n4 5 6\n";
+
+        let model = Model::from_ply_data(data.as_bytes()).unwrap();
+        assert_eq!(model.topology, MeshTopology::Points);
+        assert_eq!(model.indices, [0, 1, 2]);
+        assert_eq!(model.vertices[2].pos, glm::vec3(4.0, 5.0, 6.0));
+    }
+
+    #[test]
+    fn header() {
+        let data = b"ply\r\nformat binary_big_endian 1.0\r\nelement vertex 2\r\nproperty double x\r\nelement edge 0\r\nend_header\r\n\x01";
+        let (elements, format, body_start) = parse_ply_header(data).unwrap();
+
+        assert!(matches!(format, PlyFormat::BinaryBigEndian));
+        assert_eq!(body_start, data.len() - 1);
+        assert_eq!(elements.len(), 2);
+        assert_eq!((elements[0].name.as_str(), elements[0].count), ("vertex", 2));
+        assert!(matches!(elements[0].properties[0].kind, PlyPropertyKind::Scalar(PlyScalar::Float64)));
+        assert_eq!(elements[1].name, "edge");
+    }
+
+    #[test]
+    fn invalid_files() {
+        assert!(parse_ply_header(b"ply\nformat ascii 1.0\nelement vertex 1\n").is_err());
+        assert!(parse_ply_header(b"obj\nformat ascii 1.0\nend_header\n").is_err());
+        assert!(parse_ply_header(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty half x\nend_header\n").is_err());
+        assert!(parse_ply_header(b"ply\nelement vertex 1\nproperty float x\nend_header\n").is_err());
+
+        // Truncated body
+        let mut truncated = binary_quad(false);
+        truncated.pop();
+        assert!(Model::from_ply_data(&truncated).is_err());
+
+        // Face past the vertices
+        let data = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nelement face 1\n\
+            property list uchar int vertex_indices\nend_header\n0\n3 0 0 1\n";
+        assert!(Model::from_ply_data(data.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use nalgebra_glm as glm;
+
+use crate::*;
+
+/// Relative to the mesh extent, how far a simplified LOD may drift from the original surface
+const LOD_TARGET_ERROR: f32 = 0.05;
+
+/// How much vertex cache efficiency the overdraw optimization may give up, 1.05 is up to 5%
+const OVERDRAW_THRESHOLD: f32 = 1.05;
+
+/// Axis aligned box and bounding sphere of a set of points
+#[derive(Clone, Copy, Debug)]
+pub struct Bounds {
+    pub min: glm::Vec3,
+    pub max: glm::Vec3,
+    pub center: glm::Vec3,
+    pub radius: f32
+}
+
+impl Bounds {
+    /// `None` without points. The sphere is centered on the box, which is slightly larger than optimal but cheap.
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a glm::Vec3>) -> Option<Self> {
+        let points: Vec<&glm::Vec3> = points.into_iter().collect();
+        let first = **points.first()?;
+
+        let (min, max) = points
+            .iter()
+            .fold((first, first), |(min, max), point| (glm::min2(&min, point), glm::max2(&max, point)));
+
+        let center = (min + max) / 2.0;
+        let radius = points.iter().map(|point| glm::distance(&center, point)).fold(0.0, f32::max);
+
+        Some(Bounds { min, max, center, radius })
+    }
+
+    pub fn size(&self) -> glm::Vec3 {
+        self.max - self.min
+    }
+
+    /// Whether the box is at least partially inside the view frustum, planes are extracted from `model_view_projection`
+    /// so the test is done in object space. Works with both regular and reversed depth.
+    pub fn is_visible(&self, model_view_projection: &glm::Mat4) -> bool {
+        let row = |index: usize| model_view_projection.row(index).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let planes = [w + x, w - x, w + y, w - y, z, w - z];
+
+        planes.iter().all(|plane| {
+            // The corner furthest along the plane normal
+            let corner = glm::vec4(
+                if plane.x >= 0.0 { self.max.x } else { self.min.x },
+                if plane.y >= 0.0 { self.max.y } else { self.min.y },
+                if plane.z >= 0.0 { self.max.z } else { self.min.z },
+                1.0
+            );
+
+            plane.dot(&corner) >= 0.0
+        })
+    }
+
+    /// Distance from the center at which the bounding sphere fills a vertical field of view of `fovy` radians
+    pub fn framing_distance(&self, fovy: f32) -> f32 {
+        self.radius / (fovy / 2.0).sin()
+    }
+}
+
+/// A simplified version of a model, indexing the same vertex buffer
+#[derive(Clone, Debug)]
+pub struct Lod {
+    pub indices: Vec<u32>,
+    /// Same materials as `Model::submeshes`, over the LOD indices
+    pub submeshes: Vec<Submesh>
+}
+
+impl meshopt::DecodePosition for Vertex {
+    fn decode_position(&self) -> [f32; 3] {
+        [self.pos.x, self.pos.y, self.pos.z]
+    }
+}
+
+impl Model {
+    pub fn bounds(&self) -> Option<Bounds> {
+        Bounds::from_points(self.vertices.iter().map(|vertex| &vertex.pos))
+    }
+
+    /// Reorders triangles for the post-transform vertex cache and to reduce overdraw, then reorders vertices in the order
+    /// they are first used, dropping unused ones. Triangles never move across submeshes. Existing LODs are remapped.
+    pub fn optimize(&mut self) {
+        if self.topology == MeshTopology::Triangles {
+            for submesh in self.submeshes.iter() {
+                let range = submesh.first_index as usize..(submesh.first_index + submesh.index_count) as usize;
+
+                let mut indices = meshopt::optimize_vertex_cache(&self.indices[range.clone()], self.vertices.len());
+                meshopt::optimize_overdraw_in_place_decoder(&mut indices, &self.vertices, OVERDRAW_THRESHOLD);
+
+                self.indices[range].copy_from_slice(&indices);
+            }
+        }
+
+        let remap = meshopt::optimize_vertex_fetch_remap(&self.indices, self.vertices.len());
+        let vertex_count = remap.iter().filter(|&&index| index != u32::MAX).count();
+
+        let mut vertices = self.vertices[..vertex_count].to_vec();
+        for (old_index, &new_index) in remap.iter().enumerate() {
+            if new_index != u32::MAX {
+                vertices[new_index as usize] = self.vertices[old_index];
+            }
+        }
+
+        self.vertices = vertices;
+
+        for index in self.indices.iter_mut().chain(self.lods.iter_mut().flat_map(|lod| lod.indices.iter_mut())) {
+            *index = remap[*index as usize];
+        }
+    }
+
+    /// Replaces `lods` with up to `levels` simplified index buffers, each with `ratio` times the triangles of the previous one.
+    /// Stops early once the simplifier can't reduce the mesh any further.
+    pub fn generate_lods(&mut self, levels: usize, ratio: f32) {
+        self.lods.clear();
+
+        if self.topology != MeshTopology::Triangles {
+            return;
+        }
+
+        let mut previous = Lod {
+            indices: self.indices.clone(),
+            submeshes: self.submeshes.clone()
+        };
+
+        for _ in 0..levels {
+            let mut lod = Lod { indices: vec![], submeshes: vec![] };
+
+            for submesh in previous.submeshes.iter() {
+                let range = submesh.first_index as usize..(submesh.first_index + submesh.index_count) as usize;
+                let target_count = ((range.len() / 3) as f32 * ratio) as usize * 3;
+
+                let simplified = meshopt::simplify_decoder(&previous.indices[range], &self.vertices, target_count, LOD_TARGET_ERROR);
+                let simplified = meshopt::optimize_vertex_cache(&simplified, self.vertices.len());
+
+                lod.submeshes.push(Submesh {
+                    first_index: lod.indices.len() as u32,
+                    index_count: simplified.len() as u32,
+                    material: submesh.material
+                });
+
+                lod.indices.extend(simplified);
+            }
+
+            if lod.indices.len() >= previous.indices.len() {
+                break;
+            }
+
+            self.lods.push(lod.clone());
+            previous = lod;
+        }
+    }
+}
@@ -1,51 +1,154 @@
-use std::io::BufReader;
-use std::fs::File;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::Path;
 
+use ash::vk;
 use nalgebra_glm as glm;
 
-use crate::Vertex;
+use crate::{Lod, ObjMaterial, Vertex};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MeshTopology {
+    #[default]
+    Triangles,
+    /// Point clouds, the vertex shader sets `gl_PointSize`
+    Points
+}
+
+impl MeshTopology {
+    pub fn to_vk(self) -> vk::PrimitiveTopology {
+        match self {
+            MeshTopology::Triangles => vk::PrimitiveTopology::TRIANGLE_LIST,
+            MeshTopology::Points => vk::PrimitiveTopology::POINT_LIST
+        }
+    }
+}
+
+/// Range of `Model::indices` drawn with a single material
+#[derive(Clone, Copy, Debug)]
+pub struct Submesh {
+    pub first_index: u32,
+    pub index_count: u32,
+    /// Index into `Model::materials`
+    pub material: Option<usize>
+}
 
 pub struct Model {
     pub vertices: Vec<Vertex>,
-    pub indices: Vec<u32>
+    pub indices: Vec<u32>,
+    pub submeshes: Vec<Submesh>,
+    pub materials: Vec<ObjMaterial>,
+    pub topology: MeshTopology,
+    /// Simplified index buffers from `generate_lods`, most detailed first
+    pub lods: Vec<Lod>
 }
 
 impl Model {
-    pub fn from_file(filepath: &str) -> Self {
-        let mut reader = BufReader::new(File::open(filepath).unwrap());
-
-        let (models, _) = tobj::load_obj_buf(
-            &mut reader,
-            &tobj::LoadOptions {
-                triangulate: true,
-                ..Default::default()
-            },
-            |_| Ok(Default::default())
-        ).unwrap();
+    /// A single submesh without material covering all the indices
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        let submeshes = vec![Submesh { first_index: 0, index_count: indices.len() as u32, material: None }];
+
+        Model {
+            vertices,
+            indices,
+            submeshes,
+            materials: vec![],
+            topology: MeshTopology::Triangles,
+            lods: vec![]
+        }
+    }
+
+    /// Loads an OBJ or, based on the extension, a glTF, PLY or STL file (see `from_gltf`, `from_ply` and `from_stl`).
+    /// OBJ faces are grouped by MTL material into submeshes.
+    pub fn from_file(filepath: &str) -> Result<Self, Box<dyn Error>> {
+        let extension = Path::new(filepath)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+
+        match extension.as_deref() {
+            Some("gltf" | "glb") => return Self::from_gltf(filepath),
+            Some("ply") => return Self::from_ply(filepath),
+            Some("stl") => return Self::from_stl(filepath),
+            _ => ()
+        }
+
+        let (models, materials) = tobj::load_obj(filepath, &tobj::GPU_LOAD_OPTIONS)?;
+
+        // A missing or broken MTL file only costs the materials
+        let materials = materials.unwrap_or_default();
+
+        let mut groups: BTreeMap<Option<usize>, Vec<Vertex>> = BTreeMap::new();
+
+        for model in models {
+            let mesh = &model.mesh;
+            let triangles = groups.entry(mesh.material_id).or_default();
+
+            let has_normals = !mesh.normals.is_empty();
+            let has_texcoords = !mesh.texcoords.is_empty();
+            let has_colors = !mesh.vertex_color.is_empty();
 
+            for face in mesh.indices.chunks_exact(3) {
+                let positions = [face[0], face[1], face[2]].map(|index| {
+                    let offset = (3 * index) as usize;
+                    glm::vec3(mesh.positions[offset], mesh.positions[offset + 1], mesh.positions[offset + 2])
+                });
+
+                // Meshes exported without normals get flat shading
+                let flat_normal = face_normal(&positions[0], &positions[1], &positions[2]);
+
+                for (corner, &index) in face.iter().enumerate() {
+                    let vec3_offset = (3 * index) as usize;
+                    let uv_offset = (2 * index) as usize;
+
+                    let normal = if has_normals {
+                        glm::vec3(mesh.normals[vec3_offset], mesh.normals[vec3_offset + 1], mesh.normals[vec3_offset + 2])
+                    } else {
+                        flat_normal
+                    };
+
+                    let uv = if has_texcoords {
+                        glm::vec2(mesh.texcoords[uv_offset], 1.0 - mesh.texcoords[uv_offset + 1])
+                    } else {
+                        glm::vec2(0.0, 0.0)
+                    };
+
+                    let color = if has_colors {
+                        glm::vec4(mesh.vertex_color[vec3_offset], mesh.vertex_color[vec3_offset + 1], mesh.vertex_color[vec3_offset + 2], 1.0)
+                    } else {
+                        glm::vec4(1.0, 1.0, 1.0, 1.0)
+                    };
+
+                    triangles.push(Vertex::new(positions[corner], uv, normal, color));
+                }
+            }
+        }
+
+        let directory = Path::new(filepath).parent().unwrap_or(Path::new(""));
+
+        let mut model = Model::from_groups(groups.iter().map(|(&material, triangles)| (material, &triangles[..])));
+        model.materials = materials.iter().map(|material| ObjMaterial::from_tobj(material, directory)).collect();
+        model.compute_tangents();
+        Ok(model)
+    }
+
+    /// Builds an indexed model out of a triangle list, merging identical vertices
+    pub fn from_triangles(triangles: &[Vertex]) -> Self {
+        Self::from_groups([(None, triangles)])
+    }
+
+    /// Like `from_triangles`, with one submesh per group of triangles. Vertices are shared across submeshes.
+    pub fn from_groups<'a>(groups: impl IntoIterator<Item = (Option<usize>, &'a [Vertex])>) -> Self {
         let mut vertices = vec![];
         let mut indices = vec![];
+        let mut submeshes = vec![];
 
         let mut unique_vertices = HashMap::new();
 
-        for model in models {
-            for index in &model.mesh.indices {
-                let pos_offset = (3 * index) as usize;
-                let uv_offset = (2 * index) as usize;
-
-                let vertex = Vertex::new(
-                    glm::vec3(
-                        model.mesh.positions[pos_offset],
-                        model.mesh.positions[pos_offset + 1],
-                        model.mesh.positions[pos_offset + 2],
-                    ),
-                    glm::vec2(
-                        model.mesh.texcoords[uv_offset],
-                        1.0 - model.mesh.texcoords[uv_offset + 1]
-                    )
-                );
+        for (material, triangles) in groups {
+            let first_index = indices.len() as u32;
 
+            for &vertex in triangles {
                 if let Some(index) = unique_vertices.get(&vertex) {
                     indices.push(*index as u32);
                 } else {
@@ -55,11 +158,138 @@ impl Model {
                     indices.push(index as u32);
                 }
             }
+
+            submeshes.push(Submesh {
+                first_index,
+                index_count: indices.len() as u32 - first_index,
+                material
+            });
         }
 
         Model {
             vertices,
-            indices
+            indices,
+            submeshes,
+            materials: vec![],
+            topology: MeshTopology::Triangles,
+            lods: vec![]
+        }
+    }
+
+    /// Generates smooth per-vertex normals, weighting each face normal by the face area
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![glm::Vec3::zeros(); self.vertices.len()];
+
+        for face in self.indices.chunks_exact(3) {
+            let [a, b, c] = [face[0] as usize, face[1] as usize, face[2] as usize];
+            let normal = (self.vertices[b].pos - self.vertices[a].pos).cross(&(self.vertices[c].pos - self.vertices[a].pos));
+
+            for index in [a, b, c] {
+                normals[index] += normal;
+            }
+        }
+
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals.iter()) {
+            vertex.normal = if normal.norm_squared() > 0.0 { normal.normalize() } else { glm::vec3(0.0, 0.0, 1.0) };
+        }
+    }
+
+    /// Generates per-vertex tangents from the UV layout with MikkTSpace, so they match the ones normal maps are baked
+    /// against by Blender and most other tools. Vertices whose corners end up with different tangents, across UV seams
+    /// or mirrored UVs, are split. The handedness is stored in w.
+    pub fn compute_tangents(&mut self) {
+        let face_count = self.indices.len() / 3;
+        let mut geometry = TangentGeometry { model: self, tangents: vec![glm::Vec4::zeros(); face_count * 3] };
+
+        // Only fails without faces
+        if !bevy_mikktspace::generate_tangents(&mut geometry) {
+            return;
+        }
+
+        let tangents = geometry.tangents;
+        let mut assigned: Vec<Option<glm::Vec4>> = vec![None; self.vertices.len()];
+        let mut splits: HashMap<(u32, [u32; 4]), u32> = HashMap::new();
+
+        for (corner, tangent) in tangents.into_iter().enumerate() {
+            let index = self.indices[corner] as usize;
+
+            match assigned[index] {
+                None => {
+                    assigned[index] = Some(tangent);
+                    self.vertices[index].tangent = tangent;
+                },
+                Some(existing) if existing == tangent => (),
+                Some(_) => {
+                    let key = (index as u32, [tangent.x.to_bits(), tangent.y.to_bits(), tangent.z.to_bits(), tangent.w.to_bits()]);
+                    let vertices = &mut self.vertices;
+
+                    self.indices[corner] = *splits.entry(key).or_insert_with(|| {
+                        vertices.push(Vertex { tangent, ..vertices[index] });
+                        (vertices.len() - 1) as u32
+                    });
+                }
+            }
+        }
+
+        // Vertices no triangle uses still get a valid frame
+        for (vertex, assigned) in self.vertices.iter_mut().zip(assigned.iter()) {
+            if assigned.is_none() {
+                let orthogonal = any_orthogonal(&vertex.normal).normalize();
+                vertex.tangent = glm::vec4(orthogonal.x, orthogonal.y, orthogonal.z, 1.0);
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Triangle list view of a model for MikkTSpace, which hands back one tangent per face corner
+struct TangentGeometry<'a> {
+    model: &'a Model,
+    tangents: Vec<glm::Vec4>
+}
+
+impl TangentGeometry<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> &Vertex {
+        &self.model.vertices[self.model.indices[face * 3 + vert] as usize]
+    }
+}
+
+impl bevy_mikktspace::Geometry for TangentGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.tangents.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).pos.into()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).normal.into()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertex(face, vert).uv.into()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = tangent.into();
+    }
+}
+
+fn face_normal(a: &glm::Vec3, b: &glm::Vec3, c: &glm::Vec3) -> glm::Vec3 {
+    let normal = (b - a).cross(&(c - a));
+
+    if normal.norm_squared() > 0.0 {
+        normal.normalize()
+    } else {
+        glm::vec3(0.0, 0.0, 1.0)
+    }
+}
+
+fn any_orthogonal(normal: &glm::Vec3) -> glm::Vec3 {
+    let axis = if normal.x.abs() < 0.9 { glm::vec3(1.0, 0.0, 0.0) } else { glm::vec3(0.0, 1.0, 0.0) };
+    axis - normal * normal.dot(&axis)
+}
@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use ash::vk;
+
+use crate::*;
+
+/// Length, version, vendor ID, device ID and the cache UUID, see `VkPipelineCacheHeaderVersionOne`
+const HEADER_SIZE: usize = 16 + vk::UUID_SIZE;
+
+/// One file per GPU, the driver version is checked through the UUID in the header
+fn pipeline_cache_path(directory: &Path, properties: &vk::PhysicalDeviceProperties) -> PathBuf {
+    directory.join(format!("pipelines-{:04x}-{:04x}.bin", properties.vendor_id, properties.device_id))
+}
+
+/// Whether `data` was written by the same device and driver, drivers are supposed to reject foreign data
+/// themselves but not all of them do
+fn is_compatible(data: &[u8], properties: &vk::PhysicalDeviceProperties) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+
+    let word = |index: usize| u32::from_le_bytes(data[index * 4..index * 4 + 4].try_into().unwrap());
+
+    word(0) as usize >= HEADER_SIZE
+        && word(1) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && word(2) == properties.vendor_id
+        && word(3) == properties.device_id
+        && data[16..HEADER_SIZE] == properties.pipeline_cache_uuid
+}
+
+/// Starts from the file a previous run saved in `directory` when there is one and it is compatible, empty otherwise
+pub(crate) unsafe fn create_pipeline_cache(
+    device: &Device,
+    properties: &vk::PhysicalDeviceProperties,
+    directory: Option<&Path>
+) -> vk::PipelineCache {
+    let data = directory
+        .and_then(|directory| std::fs::read(pipeline_cache_path(directory, properties)).ok())
+        .filter(|data| is_compatible(data, properties))
+        .unwrap_or_default();
+
+    let create_info = vk::PipelineCacheCreateInfo::builder().initial_data(&data).build();
+
+    match device.create_pipeline_cache(&create_info, None) {
+        Ok(cache) => cache,
+        // Some drivers still fail on data they don't like instead of ignoring it
+        Err(_) => device.create_pipeline_cache(&vk::PipelineCacheCreateInfo::default(), None).unwrap()
+    }
+}
+
+impl Base {
+    /// Writes `pipeline_cache` to `directory` for the next run, done on drop when `Settings::pipeline_cache` is set
+    pub unsafe fn save_pipeline_cache(&self, directory: &Path) {
+        let path = pipeline_cache_path(directory, &self.device_properties);
+
+        let result = self
+            .device
+            .get_pipeline_cache_data(self.pipeline_cache)
+            .map_err(|error| error.to_string())
+            .and_then(|data| {
+                std::fs::create_dir_all(directory)
+                    .and_then(|_| std::fs::write(&path, data))
+                    .map_err(|error| error.to_string())
+            });
+
+        if let Err(error) = result {
+            println!("Failed to save pipeline cache {}: {error}", path.display());
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use ash::vk;
+
+use crate::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Opaque,
+    /// Straight alpha, `src * a + dst * (1 - a)`
+    Alpha,
+    /// `src * a + dst`, for glows and particles
+    Additive,
+    /// Color already multiplied by alpha, `src + dst * (1 - a)`
+    Premultiplied
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 4] = [BlendMode::Opaque, BlendMode::Alpha, BlendMode::Additive, BlendMode::Premultiplied];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BlendMode::Opaque => "Opaque",
+            BlendMode::Alpha => "Alpha",
+            BlendMode::Additive => "Additive",
+            BlendMode::Premultiplied => "Premultiplied"
+        }
+    }
+}
+
+/// Named starting points for `PipelineState`, listed by its `ui`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipelinePreset {
+    Opaque,
+    AlphaBlend,
+    Additive,
+    Premultiplied,
+    Wireframe,
+    DepthOnly
+}
+
+impl PipelinePreset {
+    pub const ALL: [PipelinePreset; 6] = [
+        PipelinePreset::Opaque,
+        PipelinePreset::AlphaBlend,
+        PipelinePreset::Additive,
+        PipelinePreset::Premultiplied,
+        PipelinePreset::Wireframe,
+        PipelinePreset::DepthOnly
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PipelinePreset::Opaque => "Opaque",
+            PipelinePreset::AlphaBlend => "Alpha blend",
+            PipelinePreset::Additive => "Additive",
+            PipelinePreset::Premultiplied => "Premultiplied",
+            PipelinePreset::Wireframe => "Wireframe",
+            PipelinePreset::DepthOnly => "Depth only"
+        }
+    }
+
+    pub fn state(self) -> PipelineState {
+        match self {
+            PipelinePreset::Opaque => PipelineState::opaque(),
+            PipelinePreset::AlphaBlend => PipelineState::alpha_blend(),
+            PipelinePreset::Additive => PipelineState::additive(),
+            PipelinePreset::Premultiplied => PipelineState::premultiplied(),
+            PipelinePreset::Wireframe => PipelineState::wireframe(),
+            PipelinePreset::DepthOnly => PipelineState::depth_only()
+        }
+    }
+}
+
+/// Rasterization, blend and depth state of a graphics pipeline, start from a preset and adjust with the builder
+/// methods, e.g. `PipelineState::alpha_blend().cull_mode(vk::CullModeFlags::BACK)`.
+/// Changing it means rebuilding the pipeline, which is cheap through `Base::pipeline_cache` for states seen before.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PipelineState {
+    /// `LINE` and `POINT` need `fill_mode_non_solid`, they fall back to `FILL` on devices without it
+    pub polygon_mode: vk::PolygonMode,
+    pub cull_mode: vk::CullModeFlags,
+    pub front_face: vk::FrontFace,
+    /// Widths other than 1 need `wide_lines`, they are clamped to the device's `line_width_range`
+    pub line_width: f32,
+    /// Pulls the geometry towards the camera so it wins the depth test over coplanar geometry, both off at 0.
+    /// The constant part is in depth buffer units, the slope part is scaled by how steep the polygon is.
+    pub depth_bias_constant: f32,
+    pub depth_bias_slope: f32,
+
+    pub blend: BlendMode,
+    /// Off for depth-only passes
+    pub color_write: bool,
+
+    pub depth_test: bool,
+    pub depth_write: bool,
+    /// `None` follows `Base::depth_compare_op`, which depends on `Settings::reversed_z`
+    pub depth_compare_op: Option<vk::CompareOp>
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        PipelineState::opaque()
+    }
+}
+
+impl PipelineState {
+    /// Back faces culled, depth tested and written, no blending
+    pub fn opaque() -> Self {
+        PipelineState {
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vk::CullModeFlags::BACK,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+            line_width: 1.0,
+            depth_bias_constant: 0.0,
+            depth_bias_slope: 0.0,
+
+            blend: BlendMode::Opaque,
+            color_write: true,
+
+            depth_test: true,
+            depth_write: true,
+            depth_compare_op: None
+        }
+    }
+
+    /// Both faces drawn and depth tested but not written, so draw these after the opaque geometry, back to front
+    pub fn alpha_blend() -> Self {
+        PipelineState {
+            cull_mode: vk::CullModeFlags::NONE,
+            blend: BlendMode::Alpha,
+            depth_write: false,
+            ..PipelineState::opaque()
+        }
+    }
+
+    /// Like `alpha_blend`, draw order doesn't matter
+    pub fn additive() -> Self {
+        PipelineState {
+            blend: BlendMode::Additive,
+            ..PipelineState::alpha_blend()
+        }
+    }
+
+    pub fn premultiplied() -> Self {
+        PipelineState {
+            blend: BlendMode::Premultiplied,
+            ..PipelineState::alpha_blend()
+        }
+    }
+
+    /// Triangle edges only, both faces
+    pub fn wireframe() -> Self {
+        PipelineState {
+            polygon_mode: vk::PolygonMode::LINE,
+            cull_mode: vk::CullModeFlags::NONE,
+            ..PipelineState::opaque()
+        }
+    }
+
+    /// Fills the depth buffer without touching the colors, for depth pre-passes and shadow maps
+    pub fn depth_only() -> Self {
+        PipelineState {
+            color_write: false,
+            ..PipelineState::opaque()
+        }
+    }
+
+    pub fn polygon_mode(mut self, polygon_mode: vk::PolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: vk::CullModeFlags) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn front_face(mut self, front_face: vk::FrontFace) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    pub fn line_width(mut self, line_width: f32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    pub fn depth_bias(mut self, constant: f32, slope: f32) -> Self {
+        self.depth_bias_constant = constant;
+        self.depth_bias_slope = slope;
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn color_write(mut self, color_write: bool) -> Self {
+        self.color_write = color_write;
+        self
+    }
+
+    pub fn depth_test(mut self, depth_test: bool) -> Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    pub fn depth_write(mut self, depth_write: bool) -> Self {
+        self.depth_write = depth_write;
+        self
+    }
+
+    pub fn depth_compare_op(mut self, depth_compare_op: vk::CompareOp) -> Self {
+        self.depth_compare_op = Some(depth_compare_op);
+        self
+    }
+
+    /// The preset this state is equal to, if any
+    pub fn preset(&self) -> Option<PipelinePreset> {
+        PipelinePreset::ALL.into_iter().find(|preset| preset.state() == *self)
+    }
+
+    pub fn rasterization_info(&self, base: &Base) -> vk::PipelineRasterizationStateCreateInfo {
+        let polygon_mode = if base.device_features.fill_mode_non_solid != 0 { self.polygon_mode } else { vk::PolygonMode::FILL };
+
+        let line_width = if base.device_features.wide_lines != 0 {
+            let [min, max] = base.device_properties.limits.line_width_range;
+            self.line_width.clamp(min, max)
+        } else {
+            1.0
+        };
+
+        // Closer means smaller depth values, unless they are reversed
+        let sign = if base.settings.reversed_z { 1.0 } else { -1.0 };
+
+        vk::PipelineRasterizationStateCreateInfo {
+            polygon_mode,
+            cull_mode: self.cull_mode,
+            front_face: self.front_face,
+            line_width,
+            depth_bias_enable: (self.depth_bias_constant != 0.0 || self.depth_bias_slope != 0.0) as u32,
+            depth_bias_constant_factor: sign * self.depth_bias_constant,
+            depth_bias_slope_factor: sign * self.depth_bias_slope,
+            ..Default::default()
+        }
+    }
+
+    /// The stencil test is left off
+    pub fn depth_stencil_info(&self, base: &Base) -> vk::PipelineDepthStencilStateCreateInfo {
+        let noop_stencil_state = vk::StencilOpState {
+            fail_op: vk::StencilOp::KEEP,
+            pass_op: vk::StencilOp::KEEP,
+            depth_fail_op: vk::StencilOp::KEEP,
+            compare_op: vk::CompareOp::ALWAYS,
+            ..Default::default()
+        };
+
+        vk::PipelineDepthStencilStateCreateInfo {
+            depth_test_enable: self.depth_test as u32,
+            depth_write_enable: self.depth_write as u32,
+            depth_compare_op: self.depth_compare_op.unwrap_or_else(|| base.depth_compare_op()),
+            front: noop_stencil_state,
+            back: noop_stencil_state,
+            max_depth_bounds: 1.0,
+            ..Default::default()
+        }
+    }
+
+    /// For every color attachment of the pass
+    pub fn color_blend_attachment(&self) -> vk::PipelineColorBlendAttachmentState {
+        let (src_color, dst_color, src_alpha, dst_alpha) = match self.blend {
+            BlendMode::Opaque => (vk::BlendFactor::ONE, vk::BlendFactor::ZERO, vk::BlendFactor::ONE, vk::BlendFactor::ZERO),
+            BlendMode::Alpha => (vk::BlendFactor::SRC_ALPHA, vk::BlendFactor::ONE_MINUS_SRC_ALPHA, vk::BlendFactor::ONE, vk::BlendFactor::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => (vk::BlendFactor::SRC_ALPHA, vk::BlendFactor::ONE, vk::BlendFactor::ONE, vk::BlendFactor::ONE),
+            BlendMode::Premultiplied => (vk::BlendFactor::ONE, vk::BlendFactor::ONE_MINUS_SRC_ALPHA, vk::BlendFactor::ONE, vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        };
+
+        vk::PipelineColorBlendAttachmentState {
+            blend_enable: (self.blend != BlendMode::Opaque) as u32,
+            src_color_blend_factor: src_color,
+            dst_color_blend_factor: dst_color,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: src_alpha,
+            dst_alpha_blend_factor: dst_alpha,
+            alpha_blend_op: vk::BlendOp::ADD,
+            color_write_mask: if self.color_write { vk::ColorComponentFlags::RGBA } else { vk::ColorComponentFlags::empty() }
+        }
+    }
+
+    /// Preset picker followed by the individual settings, returns whether anything changed
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let previous = *self;
+
+        let selected = self.preset().map_or("Custom", |preset| preset.name());
+        egui::ComboBox::from_label("Preset").selected_text(selected).show_ui(ui, |ui| {
+            for preset in PipelinePreset::ALL {
+                if ui.selectable_label(self.preset() == Some(preset), preset.name()).clicked() {
+                    *self = preset.state();
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.polygon_mode, vk::PolygonMode::FILL, "Fill");
+            ui.selectable_value(&mut self.polygon_mode, vk::PolygonMode::LINE, "Line");
+            ui.selectable_value(&mut self.polygon_mode, vk::PolygonMode::POINT, "Point");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Cull");
+            ui.selectable_value(&mut self.cull_mode, vk::CullModeFlags::NONE, "None");
+            ui.selectable_value(&mut self.cull_mode, vk::CullModeFlags::BACK, "Back");
+            ui.selectable_value(&mut self.cull_mode, vk::CullModeFlags::FRONT, "Front");
+        });
+
+        egui::ComboBox::from_label("Blend").selected_text(self.blend.name()).show_ui(ui, |ui| {
+            for blend in BlendMode::ALL {
+                ui.selectable_value(&mut self.blend, blend, blend.name());
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.color_write, "Color write");
+            ui.checkbox(&mut self.depth_test, "Depth test");
+            ui.checkbox(&mut self.depth_write, "Depth write");
+        });
+
+        ui.add(egui::Slider::new(&mut self.line_width, 1.0..=8.0).text("Line width"));
+        ui.add(egui::Slider::new(&mut self.depth_bias_constant, 0.0..=16.0).text("Depth bias constant"));
+        ui.add(egui::Slider::new(&mut self.depth_bias_slope, 0.0..=16.0).text("Depth bias slope"));
+
+        *self != previous
+    }
+}
@@ -0,0 +1,75 @@
+use std::error::Error;
+use std::path::Path;
+
+/// Named values that can be reset, edited through `preset_ui` and kept in a text file with one `name = value ...`
+/// line per entry. Implemented by `UniformTweaks` and `SpecializationConstants`.
+pub trait Preset {
+    /// Every entry with its current values, in the order they are saved
+    fn entries(&self) -> Vec<(String, Vec<f64>)>;
+
+    /// Unknown names are ignored, a wrong number of values is an error
+    fn set_entry(&mut self, name: &str, values: &[f64]) -> Result<(), Box<dyn Error>>;
+
+    /// Back to the default values
+    fn reset(&mut self);
+
+    fn save_preset(&self, path: &Path) -> std::io::Result<()> {
+        let lines: Vec<String> = self
+            .entries()
+            .into_iter()
+            .map(|(name, values)| {
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                format!("{name} = {}", values.join(" "))
+            })
+            .collect();
+
+        std::fs::write(path, lines.join("\n") + "\n")
+    }
+
+    /// Entries missing from the preset keep their value
+    fn load_preset(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        for line in std::fs::read_to_string(path)?.lines() {
+            let Some((name, values)) = line.split_once('=') else {
+                continue;
+            };
+
+            let values = values
+                .split_whitespace()
+                .map(|value| value.parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            self.set_entry(name.trim(), &values)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reset button, followed by Save and Load buttons when there is a `path`. Returns whether the values changed.
+pub fn preset_ui(ui: &mut egui::Ui, preset: &mut impl Preset, path: Option<&Path>) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        if ui.button("Reset").clicked() {
+            preset.reset();
+            changed = true;
+        }
+
+        if let Some(path) = path {
+            if ui.button("Save preset").clicked() {
+                if let Err(error) = preset.save_preset(path) {
+                    println!("Failed to save preset {}: {error}", path.display());
+                }
+            }
+
+            if ui.button("Load preset").clicked() {
+                match preset.load_preset(path) {
+                    Ok(()) => changed = true,
+                    Err(error) => println!("Failed to load preset {}: {error}", path.display())
+                }
+            }
+        }
+    });
+
+    changed
+}
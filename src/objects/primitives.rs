@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use nalgebra_glm as glm;
+
+use crate::*;
+
+/// Procedural shapes centered on the origin with Z up, like the examples.
+/// Faces wind counter-clockwise seen from outside, UVs have (0, 0) in the top-left corner of the texture.
+impl Model {
+    /// Grid on the XY plane facing +Z, split in `subdivisions` quads per side
+    pub fn plane(width: f32, height: f32, subdivisions: u32) -> Self {
+        let mut builder = ShapeBuilder::default();
+
+        builder.grid(subdivisions, subdivisions, |u, v| {
+            let position = glm::vec3((u - 0.5) * width, (v - 0.5) * height, 0.0);
+            (position, glm::vec3(0.0, 0.0, 1.0), glm::vec2(u, 1.0 - v))
+        });
+
+        builder.build()
+    }
+
+    /// Each face is its own grid, so edges stay sharp
+    pub fn cube(size: f32, subdivisions: u32) -> Self {
+        let mut builder = ShapeBuilder::default();
+
+        // (normal, tangent, bitangent) with tangent x bitangent = normal
+        let faces = [
+            (glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0), glm::vec3(0.0, 0.0, 1.0)),
+            (glm::vec3(-1.0, 0.0, 0.0), glm::vec3(0.0, -1.0, 0.0), glm::vec3(0.0, 0.0, 1.0)),
+            (glm::vec3(0.0, 1.0, 0.0), glm::vec3(-1.0, 0.0, 0.0), glm::vec3(0.0, 0.0, 1.0)),
+            (glm::vec3(0.0, -1.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 0.0, 1.0)),
+            (glm::vec3(0.0, 0.0, 1.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0)),
+            (glm::vec3(0.0, 0.0, -1.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, -1.0, 0.0))
+        ];
+
+        for (normal, tangent, bitangent) in faces {
+            builder.grid(subdivisions, subdivisions, |u, v| {
+                let position = (normal * 0.5 + tangent * (u - 0.5) + bitangent * (v - 0.5)) * size;
+                (position, normal, glm::vec2(u, 1.0 - v))
+            });
+        }
+
+        builder.build()
+    }
+
+    /// Sphere made of `segments` meridians and `rings` parallels, with an equirectangular UV mapping
+    pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Self {
+        let mut builder = ShapeBuilder::default();
+
+        builder.grid(segments.max(3), rings.max(2), |u, v| {
+            let (longitude, colatitude) = (u * TAU, (1.0 - v) * PI);
+            let normal = glm::vec3(colatitude.sin() * longitude.cos(), colatitude.sin() * longitude.sin(), colatitude.cos());
+            (normal * radius, normal, glm::vec2(u, 1.0 - v))
+        });
+
+        builder.build()
+    }
+
+    /// Subdivided icosahedron, evenly spread triangles unlike `uv_sphere`. Every subdivision quadruples the triangle count.
+    pub fn icosphere(radius: f32, subdivisions: u32) -> Self {
+        let t = (1.0 + 5f32.sqrt()) / 2.0;
+
+        let mut positions: Vec<glm::Vec3> = [
+            (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+            (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+            (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0)
+        ].iter().map(|&(x, y, z)| glm::vec3(x, y, z).normalize()).collect();
+
+        let mut faces: Vec<[usize; 3]> = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1]
+        ];
+
+        for _ in 0..subdivisions {
+            let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+            let mut midpoint = |a: usize, b: usize, positions: &mut Vec<glm::Vec3>| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    positions.push((positions[a] + positions[b]).normalize());
+                    positions.len() - 1
+                })
+            };
+
+            faces = faces.iter().flat_map(|&[a, b, c]| {
+                let ab = midpoint(a, b, &mut positions);
+                let bc = midpoint(b, c, &mut positions);
+                let ca = midpoint(c, a, &mut positions);
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            }).collect();
+        }
+
+        let mut triangles = vec![];
+
+        for face in faces {
+            let mut corners = face.map(|index| positions[index]);
+
+            if (corners[1] - corners[0]).cross(&(corners[2] - corners[0])).dot(&corners[0]) < 0.0 {
+                corners.swap(1, 2);
+            }
+
+            let mut uvs = corners.map(|normal| glm::vec2(0.5 + normal.y.atan2(normal.x) / TAU, normal.z.clamp(-1.0, 1.0).acos() / PI));
+
+            // Triangles crossing the seam get the wrapped side moved past u = 1
+            let (min_u, max_u) = uvs.iter().fold((f32::MAX, f32::MIN), |(min, max), uv| (min.min(uv.x), max.max(uv.x)));
+            if max_u - min_u > 0.5 {
+                for uv in uvs.iter_mut().filter(|uv| uv.x < 0.5) {
+                    uv.x += 1.0;
+                }
+            }
+
+            // The longitude is undefined at the poles, use the one of the opposite edge
+            for corner in 0..3 {
+                if corners[corner].z.abs() > 1.0 - 1e-6 {
+                    uvs[corner].x = (uvs[(corner + 1) % 3].x + uvs[(corner + 2) % 3].x) / 2.0;
+                }
+            }
+
+            for (normal, uv) in corners.iter().zip(uvs.iter()) {
+                triangles.push(Vertex::new(normal * radius, *uv, *normal, glm::vec4(1.0, 1.0, 1.0, 1.0)));
+            }
+        }
+
+        let mut model = Model::from_triangles(&triangles);
+        model.compute_tangents();
+        model
+    }
+
+    /// Cylinder along Z with capped ends, `segments` around and `height_segments` along the side
+    pub fn cylinder(radius: f32, height: f32, segments: u32, height_segments: u32) -> Self {
+        let mut builder = ShapeBuilder::default();
+        let segments = segments.max(3);
+
+        builder.grid(segments, height_segments, |u, v| {
+            let angle = u * TAU;
+            let normal = glm::vec3(angle.cos(), angle.sin(), 0.0);
+            (glm::vec3(normal.x * radius, normal.y * radius, (v - 0.5) * height), normal, glm::vec2(u, 1.0 - v))
+        });
+
+        // Caps run from the rim to the center, the parameter direction picks the facing
+        for (z, sign) in [(height / 2.0, 1.0), (-height / 2.0, -1.0)] {
+            builder.grid(segments, 1, |u, v| {
+                let angle = u * TAU;
+                let distance = if sign > 0.0 { 1.0 - v } else { v };
+                let (x, y) = (angle.cos() * distance, angle.sin() * distance);
+
+                (glm::vec3(x * radius, y * radius, z), glm::vec3(0.0, 0.0, sign), glm::vec2(0.5 + x / 2.0, 0.5 - y / 2.0))
+            });
+        }
+
+        builder.build()
+    }
+
+    /// Torus around Z, `major_segments` around the ring and `minor_segments` around the tube
+    pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> Self {
+        let mut builder = ShapeBuilder::default();
+
+        builder.grid(major_segments.max(3), minor_segments.max(3), |u, v| {
+            let (major_angle, minor_angle) = (u * TAU, v * TAU);
+            let normal = glm::vec3(minor_angle.cos() * major_angle.cos(), minor_angle.cos() * major_angle.sin(), minor_angle.sin());
+            let center = glm::vec3(major_angle.cos(), major_angle.sin(), 0.0) * major_radius;
+
+            (center + normal * minor_radius, normal, glm::vec2(u, 1.0 - v))
+        });
+
+        builder.build()
+    }
+
+    /// A single triangle covering the whole screen when its positions are passed straight through as clip coordinates.
+    /// UVs go from (0, 0) in the top-left to (1, 1) in the bottom-right corner of the screen.
+    pub fn fullscreen_triangle() -> Self {
+        let corners = [(-1.0, -1.0), (-1.0, 3.0), (3.0, -1.0)];
+
+        let vertices = corners.iter().map(|&(x, y)| {
+            Vertex::new(glm::vec3(x, y, 0.0), glm::vec2((x + 1.0) / 2.0, (y + 1.0) / 2.0), glm::vec3(0.0, 0.0, -1.0), glm::vec4(1.0, 1.0, 1.0, 1.0))
+        }).collect();
+
+        Model::new(vertices, vec![0, 1, 2])
+    }
+}
+
+#[derive(Default)]
+struct ShapeBuilder {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>
+}
+
+impl ShapeBuilder {
+    /// Appends a (u, v) grid of quads over [0, 1]², `surface` maps (u, v) to position, normal and UV.
+    /// The cross product of the u and v directions has to point outside for the faces to wind counter-clockwise.
+    fn grid<F: Fn(f32, f32) -> (glm::Vec3, glm::Vec3, glm::Vec2)>(&mut self, segments_u: u32, segments_v: u32, surface: F) {
+        let (segments_u, segments_v) = (segments_u.max(1), segments_v.max(1));
+        let first = self.vertices.len() as u32;
+
+        for j in 0..=segments_v {
+            for i in 0..=segments_u {
+                let (position, normal, uv) = surface(i as f32 / segments_u as f32, j as f32 / segments_v as f32);
+                self.vertices.push(Vertex::new(position, uv, normal, glm::vec4(1.0, 1.0, 1.0, 1.0)));
+            }
+        }
+
+        let row = segments_u + 1;
+        for j in 0..segments_v {
+            for i in 0..segments_u {
+                let a = first + j * row + i;
+                let (b, c, d) = (a + 1, a + row + 1, a + row);
+                self.indices.extend([a, b, c, a, c, d]);
+            }
+        }
+    }
+
+    fn build(self) -> Model {
+        // Quads touching the poles and the cap centers collapse into a single triangle
+        let indices = self.indices
+            .chunks_exact(3)
+            .filter(|face| {
+                let [a, b, c] = [face[0], face[1], face[2]].map(|index| self.vertices[index as usize].pos);
+                (b - a).cross(&(c - a)).norm_squared() > f32::EPSILON * f32::EPSILON
+            })
+            .flatten()
+            .copied()
+            .collect();
+
+        let mut model = Model::new(self.vertices, indices);
+        model.compute_tangents();
+        model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face_normals(model: &Model) -> Vec<(glm::Vec3, [&Vertex; 3])> {
+        model.indices.chunks_exact(3).map(|face| {
+            let corners = [face[0], face[1], face[2]].map(|index| &model.vertices[index as usize]);
+            let normal = (corners[1].pos - corners[0].pos).cross(&(corners[2].pos - corners[0].pos));
+            (normal, corners)
+        }).collect()
+    }
+
+    /// Counter-clockwise faces have their geometric normal on the side of the vertex normals
+    fn assert_winding(name: &str, model: &Model) {
+        assert!(!model.indices.is_empty(), "{name} has no faces");
+
+        for (normal, corners) in face_normals(model) {
+            assert!(normal.norm_squared() > 0.0, "{name} has a degenerate face");
+
+            for corner in corners {
+                assert!(normal.dot(&corner.normal) > 0.0, "{name} has a face wound against its normals");
+            }
+        }
+    }
+
+    /// Faces of shapes around the origin point away from it
+    fn assert_outward(name: &str, model: &Model) {
+        for (normal, corners) in face_normals(model) {
+            let center = (corners[0].pos + corners[1].pos + corners[2].pos) / 3.0;
+            assert!(normal.dot(&center) > 0.0, "{name} has a face pointing inside");
+        }
+    }
+
+    #[test]
+    fn winding() {
+        let shapes = [
+            ("plane", Model::plane(2.0, 1.0, 3)),
+            ("cube", Model::cube(1.0, 2)),
+            ("uv_sphere", Model::uv_sphere(1.0, 16, 8)),
+            ("icosphere", Model::icosphere(1.0, 2)),
+            ("cylinder", Model::cylinder(0.5, 2.0, 12, 2)),
+            ("torus", Model::torus(1.0, 0.25, 16, 8)),
+            ("fullscreen_triangle", Model::fullscreen_triangle())
+        ];
+
+        for (name, model) in shapes.iter() {
+            assert_winding(name, model);
+        }
+
+        for (name, model) in shapes[1..5].iter() {
+            assert_outward(name, model);
+        }
+    }
+
+    #[test]
+    fn tangents() {
+        for model in [Model::cube(1.0, 1), Model::uv_sphere(1.0, 12, 6), Model::torus(1.0, 0.25, 12, 6)] {
+            for vertex in model.vertices.iter() {
+                let tangent = glm::vec4_to_vec3(&vertex.tangent);
+                assert!((tangent.norm() - 1.0).abs() < 1e-3);
+                assert!(tangent.dot(&vertex.normal).abs() < 1e-3);
+                assert_eq!(vertex.tangent.w.abs(), 1.0);
+            }
+        }
+    }
+}
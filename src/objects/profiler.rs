@@ -0,0 +1,267 @@
+use std::time::{Duration, Instant};
+
+use ash::vk;
+
+use crate::*;
+
+const MAX_PASSES: u32 = 16;
+const REPORT_INTERVAL: Duration = Duration::from_millis(500);
+
+const PIPELINE_STATISTICS_COUNT: usize = 7;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PipelineStatistics {
+    pub input_assembly_vertices: u64,
+    pub input_assembly_primitives: u64,
+    pub vertex_shader_invocations: u64,
+    pub clipping_invocations: u64,
+    pub clipping_primitives: u64,
+    pub fragment_shader_invocations: u64,
+    pub compute_shader_invocations: u64
+}
+
+impl PipelineStatistics {
+    // Results are written in the order of the flag bits, which is also the order of the fields
+    fn flags() -> vk::QueryPipelineStatisticFlags {
+        vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES
+            | vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES
+            | vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS
+            | vk::QueryPipelineStatisticFlags::CLIPPING_INVOCATIONS
+            | vk::QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES
+            | vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS
+            | vk::QueryPipelineStatisticFlags::COMPUTE_SHADER_INVOCATIONS
+    }
+
+    fn from_results(results: &[u64]) -> Self {
+        PipelineStatistics {
+            input_assembly_vertices: results[0],
+            input_assembly_primitives: results[1],
+            vertex_shader_invocations: results[2],
+            clipping_invocations: results[3],
+            clipping_primitives: results[4],
+            fragment_shader_invocations: results[5],
+            compute_shader_invocations: results[6]
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PassTiming {
+    pub name: &'static str,
+    pub gpu_time: Duration,
+
+    /// `None` when the device doesn't support the `pipeline_statistics_query` feature
+    pub statistics: Option<PipelineStatistics>
+}
+
+/// Measures the CPU frame time, the GPU time and the pipeline statistics of each pass recorded between `begin_pass` and `end_pass`.
+/// Queries are read back one frame late, once the draw command buffer fence has been waited on. Passes past `MAX_PASSES` in a frame are not timed.
+pub struct Profiler {
+    timestamp_pool: vk::QueryPool,
+    timestamp_period: f32,
+    timestamps_supported: bool,
+    /// Only the low `timestamp_valid_bits` of a timestamp are meaningful
+    timestamp_mask: u64,
+
+    statistics_pool: Option<vk::QueryPool>,
+
+    recorded_passes: Vec<&'static str>,
+    /// The pass being recorded didn't fit in the query pools and isn't timed
+    skipping_pass: bool,
+    overflow_reported: bool,
+    frame_start: Instant,
+    last_report: Instant,
+
+    pub cpu_frame_time: Duration,
+    pub pass_timings: Vec<PassTiming>
+}
+
+impl Profiler {
+    pub unsafe fn new(base: &Base) -> Self {
+        let queue_family_properties = base.instance
+            .get_physical_device_queue_family_properties(base.physical_device);
+        let timestamp_valid_bits = queue_family_properties[base.queue_family_index as usize].timestamp_valid_bits;
+        let timestamps_supported = timestamp_valid_bits > 0;
+        let timestamp_mask = u64::MAX.checked_shr(64 - timestamp_valid_bits).unwrap_or(0);
+
+        let timestamp_pool_info = vk::QueryPoolCreateInfo::builder()
+            .query_type(vk::QueryType::TIMESTAMP)
+            .query_count(MAX_PASSES * 2)
+            .build();
+
+        let timestamp_pool = base.device.create_query_pool(&timestamp_pool_info, None).unwrap();
+
+        let statistics_pool = if base.device_features.pipeline_statistics_query == vk::TRUE {
+            let statistics_pool_info = vk::QueryPoolCreateInfo::builder()
+                .query_type(vk::QueryType::PIPELINE_STATISTICS)
+                .pipeline_statistics(PipelineStatistics::flags())
+                .query_count(MAX_PASSES)
+                .build();
+
+            Some(base.device.create_query_pool(&statistics_pool_info, None).unwrap())
+        } else {
+            None
+        };
+
+        Profiler {
+            timestamp_pool,
+            timestamp_period: base.device_properties.limits.timestamp_period,
+            timestamps_supported,
+            timestamp_mask,
+
+            statistics_pool,
+
+            recorded_passes: vec![],
+            skipping_pass: false,
+            overflow_reported: false,
+            frame_start: Instant::now(),
+            last_report: Instant::now(),
+
+            cpu_frame_time: Duration::ZERO,
+            pass_timings: vec![]
+        }
+    }
+
+    /// Collects the previous frame results and resets the queries, must be recorded outside of any render pass
+    pub unsafe fn begin_frame(&mut self, device: &Device, command_buffer: vk::CommandBuffer) {
+        let now = Instant::now();
+        self.cpu_frame_time = now - self.frame_start;
+        self.frame_start = now;
+
+        if !self.recorded_passes.is_empty() {
+            let pass_count = self.recorded_passes.len();
+            let mut timestamps = vec![0u64; pass_count * 2];
+            let mut statistics = vec![[0u64; PIPELINE_STATISTICS_COUNT]; pass_count];
+
+            if self.timestamps_supported {
+                device.get_query_pool_results(
+                    self.timestamp_pool,
+                    0,
+                    pass_count as u32 * 2,
+                    &mut timestamps,
+                    vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT
+                ).unwrap();
+            }
+
+            // Each statistics query writes one value per enabled flag, hence the array stride
+            if let Some(statistics_pool) = self.statistics_pool {
+                device.get_query_pool_results(
+                    statistics_pool,
+                    0,
+                    pass_count as u32,
+                    &mut statistics,
+                    vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT
+                ).unwrap();
+            }
+
+            self.pass_timings = self.recorded_passes
+                .iter()
+                .zip(timestamps.chunks_exact(2))
+                .zip(statistics.iter())
+                .map(|((&name, range), statistics)| PassTiming {
+                    name,
+                    gpu_time: Duration::from_nanos(
+                        (self.timestamp_ticks(range[0], range[1]) as f64 * self.timestamp_period as f64) as u64
+                    ),
+                    statistics: self.statistics_pool.map(|_| PipelineStatistics::from_results(statistics))
+                })
+                .collect();
+        }
+
+        self.recorded_passes.clear();
+        device.cmd_reset_query_pool(command_buffer, self.timestamp_pool, 0, MAX_PASSES * 2);
+
+        if let Some(statistics_pool) = self.statistics_pool {
+            device.cmd_reset_query_pool(command_buffer, statistics_pool, 0, MAX_PASSES);
+        }
+    }
+
+    /// Ticks between two timestamps, masked to the valid bits so a counter that wrapped around still measures right
+    fn timestamp_ticks(&self, start: u64, end: u64) -> u64 {
+        (end & self.timestamp_mask).wrapping_sub(start & self.timestamp_mask) & self.timestamp_mask
+    }
+
+    pub unsafe fn begin_pass(&mut self, device: &Device, command_buffer: vk::CommandBuffer, name: &'static str) {
+        if self.recorded_passes.len() as u32 >= MAX_PASSES {
+            if !self.overflow_reported {
+                println!("More than {MAX_PASSES} passes in a frame, \"{name}\" and the following ones are not profiled");
+                self.overflow_reported = true;
+            }
+
+            self.skipping_pass = true;
+            return;
+        }
+
+        let query = self.recorded_passes.len() as u32 * 2;
+        self.recorded_passes.push(name);
+
+        if self.timestamps_supported {
+            device.cmd_write_timestamp(command_buffer, vk::PipelineStageFlags::TOP_OF_PIPE, self.timestamp_pool, query);
+        }
+
+        if let Some(statistics_pool) = self.statistics_pool {
+            device.cmd_begin_query(command_buffer, statistics_pool, query / 2, vk::QueryControlFlags::empty());
+        }
+    }
+
+    pub unsafe fn end_pass(&mut self, device: &Device, command_buffer: vk::CommandBuffer) {
+        if self.skipping_pass {
+            self.skipping_pass = false;
+            return;
+        }
+
+        let query = self.recorded_passes.len() as u32 * 2 - 1;
+
+        if let Some(statistics_pool) = self.statistics_pool {
+            device.cmd_end_query(command_buffer, statistics_pool, query / 2);
+        }
+
+        if self.timestamps_supported {
+            device.cmd_write_timestamp(command_buffer, vk::PipelineStageFlags::BOTTOM_OF_PIPE, self.timestamp_pool, query);
+        }
+    }
+
+    /// Returns a summary line at most every `REPORT_INTERVAL`, so the window title stays readable
+    pub fn report(&mut self) -> Option<String> {
+        if self.last_report.elapsed() < REPORT_INTERVAL {
+            return None;
+        }
+
+        self.last_report = Instant::now();
+        Some(self.summary())
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "CPU {:.2} ms ({:.0} fps)",
+            self.cpu_frame_time.as_secs_f64() * 1000.0,
+            1.0 / self.cpu_frame_time.as_secs_f64().max(f64::EPSILON)
+        );
+
+        for timing in self.pass_timings.iter() {
+            summary += &format!(" | {} {:.3} ms", timing.name, timing.gpu_time.as_secs_f64() * 1000.0);
+
+            if let Some(statistics) = timing.statistics.filter(|statistics| statistics.compute_shader_invocations > 0) {
+                summary += &format!(" ({} cs)", statistics.compute_shader_invocations);
+            } else if let Some(statistics) = timing.statistics {
+                summary += &format!(
+                    " ({} vs, {} prims, {} clipped, {} fs)",
+                    statistics.vertex_shader_invocations,
+                    statistics.input_assembly_primitives,
+                    statistics.clipping_primitives,
+                    statistics.fragment_shader_invocations
+                );
+            }
+        }
+
+        summary
+    }
+
+    pub unsafe fn destroy(&self, base: &Base) {
+        base.device.destroy_query_pool(self.timestamp_pool, None);
+
+        if let Some(statistics_pool) = self.statistics_pool {
+            base.device.destroy_query_pool(statistics_pool, None);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use ash::vk;
+
+use crate::*;
+
+/// Range covering a `T` at `offset`, for pipelines whose push constants are declared on the Rust side
+pub fn push_constant_range<T: Copy>(stage_flags: vk::ShaderStageFlags, offset: u32) -> vk::PushConstantRange {
+    vk::PushConstantRange {
+        stage_flags,
+        offset,
+        size: std::mem::size_of::<T>() as u32
+    }
+}
+
+/// Ranges of all the stages of a pipeline, stages reading the same range share an entry.
+/// Only for layouts without the globals set, the others use `Globals::push_constant_range`.
+pub fn push_constant_ranges(reflections: &[&ShaderReflection]) -> Vec<vk::PushConstantRange> {
+    let mut ranges: Vec<vk::PushConstantRange> = vec![];
+
+    for range in reflections.iter().filter_map(|reflection| reflection.push_constant_range()) {
+        match ranges.iter_mut().find(|existing| existing.offset == range.offset && existing.size == range.size) {
+            Some(existing) => existing.stage_flags |= range.stage_flags,
+            None => ranges.push(range)
+        }
+    }
+
+    ranges
+}
+
+/// Records `value` as push constants at `offset`. `T` has to match the shader block layout, which for push constants
+/// is std430: `#[repr(C)]` structs of scalars, vec2, vec4 and mat4 line up, vec3 needs padding.
+pub unsafe fn cmd_push_constants<T: Copy>(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    layout: vk::PipelineLayout,
+    stage_flags: vk::ShaderStageFlags,
+    offset: u32,
+    value: &T
+) {
+    device.cmd_push_constants(command_buffer, layout, stage_flags, offset, push_constant_bytes(value));
+}
+
+/// Raw bytes of `value`, e.g. for `ComputePass::push_constants`, same layout rules as `cmd_push_constants`
+pub fn push_constant_bytes<T: Copy>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts((value as *const T).cast::<u8>(), std::mem::size_of::<T>()) }
+}
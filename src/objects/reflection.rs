@@ -0,0 +1,468 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use ash::vk;
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+// Opcodes
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_SPEC_CONSTANT_TRUE: u32 = 48;
+const OP_SPEC_CONSTANT_FALSE: u32 = 49;
+const OP_SPEC_CONSTANT: u32 = 50;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+// Decorations
+const DECORATION_SPEC_ID: u32 = 1;
+const DECORATION_BLOCK: u32 = 2;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+// Storage classes
+const STORAGE_CLASS_INPUT: u32 = 1;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalarKind {
+    Bool,
+    Int { signed: bool },
+    Float
+}
+
+#[derive(Clone, Debug)]
+enum Type {
+    Scalar { kind: ScalarKind, width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Array { element: u32, length: u32 },
+    Struct { members: Vec<u32> },
+    Pointer { pointee: u32 }
+}
+
+/// Type of a uniform block member, 32 bit components only
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UniformType {
+    Scalar(ScalarKind),
+    Vector(ScalarKind, u32),
+    Matrix { columns: u32, rows: u32 },
+    /// Arrays, nested structs and anything wider than 32 bits, only their size is known
+    Other
+}
+
+#[derive(Clone, Debug)]
+pub struct UniformMember {
+    pub name: String,
+    /// In bytes from the start of the block
+    pub offset: u32,
+    pub size: u32,
+    pub ty: UniformType
+}
+
+#[derive(Clone, Debug)]
+pub struct UniformBlock {
+    /// Name of the block type, `UniformBufferObject` in `uniform UniformBufferObject { ... } ubo`
+    pub name: String,
+    pub set: u32,
+    pub binding: u32,
+    /// Up to the end of the last member
+    pub size: u32,
+    pub members: Vec<UniformMember>
+}
+
+#[derive(Clone, Debug)]
+pub struct ShaderInput {
+    pub name: String,
+    pub location: u32,
+    pub format: vk::Format
+}
+
+#[derive(Clone, Debug)]
+pub struct PushConstantBlock {
+    pub name: String,
+    /// Of the first member, stages can each use a different part of the push constant range
+    pub offset: u32,
+    pub size: u32,
+    pub members: Vec<UniformMember>
+}
+
+/// A `layout(constant_id = N) const` declaration, 32 bit scalars only
+#[derive(Clone, Debug)]
+pub struct SpecializationConstant {
+    pub name: String,
+    pub id: u32,
+    pub kind: ScalarKind,
+    /// Bits of the value compiled into the shader, bools are 0 or 1
+    pub default: u32
+}
+
+/// The parts of a SPIR-V module Tarsier needs to build pipelines without hard-coding the shader interface
+#[derive(Clone, Debug, Default)]
+pub struct ShaderReflection {
+    pub stage: vk::ShaderStageFlags,
+    pub entry_point: String,
+    /// Vertex attributes, one per location, empty for the other stages
+    pub inputs: Vec<ShaderInput>,
+    /// Sorted by set and binding
+    pub uniform_blocks: Vec<UniformBlock>,
+    pub push_constants: Option<PushConstantBlock>,
+    /// Sorted by constant ID
+    pub specialization_constants: Vec<SpecializationConstant>
+}
+
+impl ShaderReflection {
+    pub fn from_spirv(code: &[u32]) -> Result<Self, Box<dyn Error>> {
+        if code.len() < 5 || code[0] != SPIRV_MAGIC {
+            return Err("Not a SPIR-V module".into());
+        }
+
+        let mut reflection = ShaderReflection::default();
+
+        let mut names: HashMap<u32, String> = HashMap::new();
+        let mut member_names: HashMap<(u32, u32), String> = HashMap::new();
+        let mut decorations: HashMap<u32, HashMap<u32, u32>> = HashMap::new();
+        let mut member_decorations: HashMap<(u32, u32), HashMap<u32, u32>> = HashMap::new();
+        let mut types: HashMap<u32, Type> = HashMap::new();
+        let mut constants: HashMap<u32, u32> = HashMap::new();
+        let mut variables: Vec<(u32, u32, u32)> = vec![];
+        let mut spec_constants: Vec<(u32, u32, u32)> = vec![];
+
+        let mut offset = 5;
+        while offset < code.len() {
+            let word_count = (code[offset] >> 16) as usize;
+            let opcode = code[offset] & 0xffff;
+
+            if word_count == 0 || offset + word_count > code.len() {
+                return Err("Malformed SPIR-V instruction".into());
+            }
+
+            let operands = &code[offset + 1..offset + word_count];
+
+            match opcode {
+                OP_NAME => {
+                    names.insert(operands[0], parse_string(&operands[1..]));
+                },
+                OP_MEMBER_NAME => {
+                    member_names.insert((operands[0], operands[1]), parse_string(&operands[2..]));
+                },
+                OP_ENTRY_POINT if reflection.entry_point.is_empty() => {
+                    reflection.stage = execution_model_stage(operands[0]);
+                    reflection.entry_point = parse_string(&operands[2..]);
+                },
+                OP_DECORATE => {
+                    let value = operands.get(2).cloned().unwrap_or(0);
+                    decorations.entry(operands[0]).or_default().insert(operands[1], value);
+                },
+                OP_MEMBER_DECORATE => {
+                    let value = operands.get(3).cloned().unwrap_or(0);
+                    member_decorations.entry((operands[0], operands[1])).or_default().insert(operands[2], value);
+                },
+                OP_TYPE_BOOL => {
+                    types.insert(operands[0], Type::Scalar { kind: ScalarKind::Bool, width: 32 });
+                },
+                OP_TYPE_INT => {
+                    types.insert(operands[0], Type::Scalar { kind: ScalarKind::Int { signed: operands[2] != 0 }, width: operands[1] });
+                },
+                OP_TYPE_FLOAT => {
+                    types.insert(operands[0], Type::Scalar { kind: ScalarKind::Float, width: operands[1] });
+                },
+                OP_TYPE_VECTOR => {
+                    types.insert(operands[0], Type::Vector { component: operands[1], count: operands[2] });
+                },
+                OP_TYPE_MATRIX => {
+                    types.insert(operands[0], Type::Matrix { column: operands[1], count: operands[2] });
+                },
+                OP_TYPE_ARRAY => {
+                    types.insert(operands[0], Type::Array { element: operands[1], length: operands[2] });
+                },
+                OP_TYPE_STRUCT => {
+                    types.insert(operands[0], Type::Struct { members: operands[1..].to_vec() });
+                },
+                OP_CONSTANT => {
+                    // Only 32 bit constants matter here, as array lengths
+                    constants.insert(operands[1], operands[2]);
+                },
+                OP_SPEC_CONSTANT_TRUE | OP_SPEC_CONSTANT_FALSE => {
+                    spec_constants.push((operands[0], operands[1], (opcode == OP_SPEC_CONSTANT_TRUE) as u32));
+                },
+                OP_SPEC_CONSTANT => {
+                    // (result type, result id, value)
+                    spec_constants.push((operands[0], operands[1], operands[2]));
+                },
+                OP_TYPE_POINTER => {
+                    types.insert(operands[0], Type::Pointer { pointee: operands[2] });
+                },
+                OP_VARIABLE => {
+                    // (result type, result id, storage class)
+                    variables.push((operands[0], operands[1], operands[2]));
+                },
+                _ => ()
+            }
+
+            offset += word_count;
+        }
+
+        // Only vertex inputs are fed from buffers, the inputs of later stages can be any type
+        let vertex_variables = variables.iter().filter(|_| reflection.stage == vk::ShaderStageFlags::VERTEX);
+
+        for &(pointer_type, id, storage_class) in vertex_variables {
+            let variable_decorations = decorations.get(&id);
+
+            if storage_class != STORAGE_CLASS_INPUT
+                || variable_decorations.is_some_and(|d| d.contains_key(&DECORATION_BUILT_IN)) {
+                continue;
+            }
+
+            let Some(&location) = variable_decorations.and_then(|d| d.get(&DECORATION_LOCATION)) else {
+                continue;
+            };
+
+            let Some(Type::Pointer { pointee }) = types.get(&pointer_type) else {
+                continue;
+            };
+
+            let formats = vertex_formats(&types, &constants, *pointee)
+                .ok_or_else(|| format!("Unsupported type for vertex shader input at location {location}"))?;
+
+            // Matrices and arrays take one location per column or element
+            for (index, format) in formats.into_iter().enumerate() {
+                reflection.inputs.push(ShaderInput {
+                    name: names.get(&id).cloned().unwrap_or_default(),
+                    location: location + index as u32,
+                    format
+                });
+            }
+        }
+
+        reflection.inputs.sort_by_key(|input| input.location);
+
+        let layout = Layout {
+            types: &types,
+            constants: &constants,
+            decorations: &decorations,
+            member_decorations: &member_decorations,
+            member_names: &member_names
+        };
+
+        for &(pointer_type, id, storage_class) in variables.iter() {
+            let Some(Type::Pointer { pointee }) = types.get(&pointer_type) else {
+                continue;
+            };
+
+            if storage_class == STORAGE_CLASS_PUSH_CONSTANT {
+                let members = layout.members(*pointee);
+                let offset = members.iter().map(|member| member.offset).min().unwrap_or(0);
+                let end = members.iter().map(|member| member.offset + member.size).max().unwrap_or(0);
+
+                reflection.push_constants = Some(PushConstantBlock {
+                    name: names.get(pointee).cloned().unwrap_or_default(),
+                    offset,
+                    size: end - offset,
+                    members
+                });
+
+                continue;
+            }
+
+            // Storage buffers use the uniform storage class too in older SPIR-V, but they are decorated `BufferBlock`
+            let is_block = decorations.get(pointee).is_some_and(|d| d.contains_key(&DECORATION_BLOCK));
+
+            if storage_class != STORAGE_CLASS_UNIFORM || !is_block {
+                continue;
+            }
+
+            let decoration = |decoration: u32| decorations.get(&id).and_then(|d| d.get(&decoration)).cloned().unwrap_or(0);
+            let members = layout.members(*pointee);
+
+            reflection.uniform_blocks.push(UniformBlock {
+                name: names.get(pointee).cloned().unwrap_or_default(),
+                set: decoration(DECORATION_DESCRIPTOR_SET),
+                binding: decoration(DECORATION_BINDING),
+                size: members.iter().map(|member| member.offset + member.size).max().unwrap_or(0),
+                members
+            });
+        }
+
+        reflection.uniform_blocks.sort_by_key(|block| (block.set, block.binding));
+
+        for &(result_type, id, default) in spec_constants.iter() {
+            // Constants derived from others through `OpSpecConstantOp` have no ID of their own
+            let Some(&constant_id) = decorations.get(&id).and_then(|d| d.get(&DECORATION_SPEC_ID)) else {
+                continue;
+            };
+
+            let Some(&Type::Scalar { kind, width: 32 }) = types.get(&result_type) else {
+                continue;
+            };
+
+            reflection.specialization_constants.push(SpecializationConstant {
+                name: names.get(&id).cloned().unwrap_or_default(),
+                id: constant_id,
+                kind,
+                default
+            });
+        }
+
+        reflection.specialization_constants.sort_by_key(|constant| constant.id);
+
+        Ok(reflection)
+    }
+
+    /// The part of the push constants this stage reads, `None` if it doesn't use any
+    pub fn push_constant_range(&self) -> Option<vk::PushConstantRange> {
+        self.push_constants.as_ref().map(|block| vk::PushConstantRange {
+            stage_flags: self.stage,
+            offset: block.offset,
+            size: block.size
+        })
+    }
+}
+
+/// Everything needed to work out the memory layout of a block
+struct Layout<'a> {
+    types: &'a HashMap<u32, Type>,
+    constants: &'a HashMap<u32, u32>,
+    decorations: &'a HashMap<u32, HashMap<u32, u32>>,
+    member_decorations: &'a HashMap<(u32, u32), HashMap<u32, u32>>,
+    member_names: &'a HashMap<(u32, u32), String>
+}
+
+impl Layout<'_> {
+    fn members(&self, struct_type: u32) -> Vec<UniformMember> {
+        let Some(Type::Struct { members }) = self.types.get(&struct_type) else {
+            return vec![];
+        };
+
+        members.iter().enumerate().map(|(index, &member_type)| {
+            let key = (struct_type, index as u32);
+            let decoration = |decoration: u32| self.member_decorations.get(&key).and_then(|d| d.get(&decoration)).cloned();
+
+            UniformMember {
+                name: self.member_names.get(&key).cloned().unwrap_or_default(),
+                offset: decoration(DECORATION_OFFSET).unwrap_or(0),
+                size: self.size(member_type, decoration(DECORATION_MATRIX_STRIDE)),
+                ty: self.uniform_type(member_type)
+            }
+        }).collect()
+    }
+
+    /// `matrix_stride` comes from the member holding the matrix
+    fn size(&self, type_id: u32, matrix_stride: Option<u32>) -> u32 {
+        match self.types.get(&type_id) {
+            Some(Type::Scalar { width, .. }) => width / 8,
+            Some(Type::Vector { component, count }) => self.size(*component, None) * count,
+            Some(Type::Matrix { column, count }) => matrix_stride.unwrap_or_else(|| self.size(*column, None)) * count,
+            Some(Type::Array { element, length }) => {
+                let stride = self.decorations
+                    .get(&type_id)
+                    .and_then(|d| d.get(&DECORATION_ARRAY_STRIDE))
+                    .cloned()
+                    .unwrap_or_else(|| self.size(*element, matrix_stride));
+
+                stride * self.constants.get(length).cloned().unwrap_or(0)
+            },
+            Some(Type::Struct { .. }) => self.members(type_id).iter().map(|member| member.offset + member.size).max().unwrap_or(0),
+            Some(Type::Pointer { .. }) => 8,
+            None => 0
+        }
+    }
+
+    fn uniform_type(&self, type_id: u32) -> UniformType {
+        let scalar = |type_id: u32| match self.types.get(&type_id) {
+            Some(Type::Scalar { kind, width: 32 }) => Some(*kind),
+            _ => None
+        };
+
+        match self.types.get(&type_id) {
+            Some(Type::Scalar { .. }) => scalar(type_id).map_or(UniformType::Other, UniformType::Scalar),
+            Some(Type::Vector { component, count }) => scalar(*component).map_or(UniformType::Other, |kind| UniformType::Vector(kind, *count)),
+            Some(Type::Matrix { column, count }) => match self.types.get(column) {
+                Some(Type::Vector { count: rows, .. }) => UniformType::Matrix { columns: *count, rows: *rows },
+                _ => UniformType::Other
+            },
+            _ => UniformType::Other
+        }
+    }
+}
+
+fn parse_string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .take_while(|&byte| byte != 0)
+        .collect();
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn execution_model_stage(execution_model: u32) -> vk::ShaderStageFlags {
+    match execution_model {
+        0 => vk::ShaderStageFlags::VERTEX,
+        1 => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+        2 => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+        3 => vk::ShaderStageFlags::GEOMETRY,
+        4 => vk::ShaderStageFlags::FRAGMENT,
+        5 => vk::ShaderStageFlags::COMPUTE,
+        _ => vk::ShaderStageFlags::empty()
+    }
+}
+
+/// Formats of the consecutive locations an input of `type_id` takes
+fn vertex_formats(types: &HashMap<u32, Type>, constants: &HashMap<u32, u32>, type_id: u32) -> Option<Vec<vk::Format>> {
+    match types.get(&type_id)? {
+        Type::Matrix { column, count } => Some(vec![vertex_format(types, *column)?; *count as usize]),
+        Type::Array { element, length } => {
+            let element_formats = vertex_formats(types, constants, *element)?;
+            Some(element_formats.repeat(*constants.get(length)? as usize))
+        },
+        _ => Some(vec![vertex_format(types, type_id)?])
+    }
+}
+
+fn vertex_format(types: &HashMap<u32, Type>, type_id: u32) -> Option<vk::Format> {
+    let (component, count) = match types.get(&type_id)? {
+        Type::Vector { component, count } => (*component, *count),
+        Type::Scalar { .. } => (type_id, 1),
+        _ => return None
+    };
+
+    let Type::Scalar { kind, width: 32 } = types.get(&component)? else {
+        return None;
+    };
+
+    let format = match (kind, count) {
+        (ScalarKind::Float, 1) => vk::Format::R32_SFLOAT,
+        (ScalarKind::Float, 2) => vk::Format::R32G32_SFLOAT,
+        (ScalarKind::Float, 3) => vk::Format::R32G32B32_SFLOAT,
+        (ScalarKind::Float, 4) => vk::Format::R32G32B32A32_SFLOAT,
+        (ScalarKind::Int { signed: true }, 1) => vk::Format::R32_SINT,
+        (ScalarKind::Int { signed: true }, 2) => vk::Format::R32G32_SINT,
+        (ScalarKind::Int { signed: true }, 3) => vk::Format::R32G32B32_SINT,
+        (ScalarKind::Int { signed: true }, 4) => vk::Format::R32G32B32A32_SINT,
+        (ScalarKind::Int { signed: false }, 1) => vk::Format::R32_UINT,
+        (ScalarKind::Int { signed: false }, 2) => vk::Format::R32G32_UINT,
+        (ScalarKind::Int { signed: false }, 3) => vk::Format::R32G32B32_UINT,
+        (ScalarKind::Int { signed: false }, 4) => vk::Format::R32G32B32A32_UINT,
+        _ => return None
+    };
+
+    Some(format)
+}
@@ -0,0 +1,287 @@
+use ash::vk;
+
+use crate::*;
+
+pub struct Renderer {
+    pub renderpass: vk::RenderPass,
+    pub framebuffers: Vec<vk::Framebuffer>,
+
+    pub viewports: [vk::Viewport; 1],
+    pub scissors: [vk::Rect2D; 1],
+
+    pub clear_color: [f32; 4],
+
+    pub profiler: Profiler,
+    pub globals: Globals,
+    pub ui: UiOverlay
+}
+
+impl Renderer {
+    pub unsafe fn new(base: &Base) -> Self {
+        // ================================================================
+        // RENDERPASS
+        // ================================================================
+
+        let multisampled = base.msaa_samples != vk::SampleCountFlags::TYPE_1;
+
+        // When multisampling, the color attachment lives in a transient image and gets resolved to the swapchain one.
+        // The swapchain image is left for the UI pass, which moves it to the present layout.
+        let mut renderpass_attachments = vec![
+            vk::AttachmentDescription {
+                format: base.surface_format.format,
+                samples: base.msaa_samples,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: if multisampled { vk::AttachmentStoreOp::DONT_CARE } else { vk::AttachmentStoreOp::STORE },
+                final_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                ..Default::default()
+            },
+
+            vk::AttachmentDescription {
+                format: base.depth_format,
+                samples: base.msaa_samples,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                stencil_load_op: base.settings.stencil_load_op,
+                stencil_store_op: base.settings.stencil_store_op,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                ..Default::default()
+            }
+        ];
+
+        if multisampled {
+            renderpass_attachments.push(vk::AttachmentDescription {
+                format: base.surface_format.format,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::DONT_CARE,
+                store_op: vk::AttachmentStoreOp::STORE,
+                final_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                ..Default::default()
+            });
+        }
+
+        let color_attachment_refs = [vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+        }];
+
+        let depth_attachment_ref = vk::AttachmentReference {
+            attachment: 1,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+        };
+
+        let resolve_attachment_refs = [vk::AttachmentReference {
+            attachment: 2,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+        }];
+
+        // The depth image is shared between frames, so the previous frame's depth writes have to complete first
+        let dependencies = [vk::SubpassDependency {
+            src_subpass: vk::SUBPASS_EXTERNAL,
+            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            src_access_mask: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ
+                | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+            ..Default::default()
+        }];
+
+        let mut subpass_builder = vk::SubpassDescription::builder()
+            .color_attachments(&color_attachment_refs)
+            .depth_stencil_attachment(&depth_attachment_ref)
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS);
+
+        if multisampled {
+            subpass_builder = subpass_builder.resolve_attachments(&resolve_attachment_refs);
+        }
+
+        let subpass = subpass_builder.build();
+
+        let renderpass_create_info = vk::RenderPassCreateInfo::builder()
+            .attachments(&renderpass_attachments)
+            .subpasses(std::slice::from_ref(&subpass))
+            .dependencies(&dependencies)
+            .build();
+
+        let renderpass = base.device.create_render_pass(&renderpass_create_info, None).unwrap();
+
+        let mut renderer = Renderer {
+            renderpass,
+            framebuffers: vec![],
+
+            viewports: [vk::Viewport::default()],
+            scissors: [vk::Rect2D::default()],
+
+            clear_color: [0.14, 0.15, 0.2, 0.0],
+
+            profiler: Profiler::new(base),
+            globals: Globals::new(base),
+            ui: UiOverlay::new(base)
+        };
+
+        renderer.create_framebuffers(base);
+        renderer
+    }
+
+    /// Rebuilds the framebuffers and the viewport after `Base::recreate_swapchain`
+    pub unsafe fn resize(&mut self, base: &Base) {
+        self.destroy_framebuffers(base);
+        self.create_framebuffers(base);
+        self.ui.resize(base);
+    }
+
+    unsafe fn create_framebuffers(&mut self, base: &Base) {
+        let multisampled = base.msaa_samples != vk::SampleCountFlags::TYPE_1;
+
+        self.framebuffers = base
+            .present_image_views
+            .iter()
+            .map(|&present_image_view| {
+                let framebuffer_attachments = if multisampled {
+                    vec![base.color_image_view, base.depth_image_view, present_image_view]
+                } else {
+                    vec![present_image_view, base.depth_image_view]
+                };
+
+                let framebuffer_create_info = vk::FramebufferCreateInfo::builder()
+                    .render_pass(self.renderpass)
+                    .attachments(&framebuffer_attachments)
+                    .width(base.surface_resolution.width)
+                    .height(base.surface_resolution.height)
+                    .layers(1)
+                    .build();
+
+                base.device.create_framebuffer(&framebuffer_create_info, None).unwrap()
+            })
+            .collect();
+
+        self.viewports = [vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: base.surface_resolution.width as f32,
+            height: base.surface_resolution.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0
+        }];
+        self.scissors = [base.surface_resolution.into()];
+    }
+
+    unsafe fn destroy_framebuffers(&mut self, base: &Base) {
+        for &framebuffer in self.framebuffers.iter() {
+            base.device.destroy_framebuffer(framebuffer, None);
+        }
+    }
+
+    /// Acquires the next swapchain image, records `compute_passes` followed by `f` inside the main render pass
+    /// and presents the result. Flags the swapchain as outdated when it no longer matches the surface.
+    pub unsafe fn draw_frame<F: FnOnce(&Device, vk::CommandBuffer)>(&mut self, base: &mut Base, compute_passes: &[ComputePass], f: F) {
+        let present_index = match base.swapchain_loader.acquire_next_image(
+            base.swapchain,
+            u64::MAX,
+            base.present_complete_semaphore,
+            vk::Fence::null()
+        ) {
+            Ok((present_index, _)) => present_index,
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                base.swapchain_outdated = true;
+                return;
+            },
+            Err(error) => panic!("Could not acquire swapchain image: {error}")
+        };
+
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue { float32: self.clear_color }
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: base.depth_clear_value(),
+                    stencil: 0
+                }
+            }
+        ];
+
+        let render_pass_begin_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(self.renderpass)
+            .framebuffer(self.framebuffers[present_index as usize])
+            .render_area(base.surface_resolution.into())
+            .clear_values(&clear_values)
+            .build();
+
+        record_submit_commandbuffer(
+            &base.device,
+            base.draw_command_buffer,
+            base.draw_commands_reuse_fence,
+            base.present_queue,
+            &[vk::PipelineStageFlags::BOTTOM_OF_PIPE],
+            &[base.present_complete_semaphore],
+            &[base.rendering_complete_semaphore],
+            |device, draw_command_buffer| {
+                self.profiler.begin_frame(device, draw_command_buffer);
+
+                self.globals.bind(device, draw_command_buffer);
+
+                for (index, pass) in compute_passes.iter().enumerate() {
+                    if index > 0 {
+                        compute_to_compute_barrier(device, draw_command_buffer);
+                    }
+
+                    self.profiler.begin_pass(device, draw_command_buffer, pass.name);
+                    pass.record(device, draw_command_buffer);
+                    self.profiler.end_pass(device, draw_command_buffer);
+                }
+
+                if !compute_passes.is_empty() {
+                    compute_to_graphics_barrier(device, draw_command_buffer);
+                }
+
+                self.profiler.begin_pass(device, draw_command_buffer, "main");
+
+                device.cmd_begin_render_pass(draw_command_buffer, &render_pass_begin_info, vk::SubpassContents::INLINE);
+
+                device.cmd_set_viewport(draw_command_buffer, 0, &self.viewports);
+                device.cmd_set_scissor(draw_command_buffer, 0, &self.scissors);
+
+                f(device, draw_command_buffer);
+
+                device.cmd_end_render_pass(draw_command_buffer);
+
+                self.profiler.end_pass(device, draw_command_buffer);
+
+                self.profiler.begin_pass(device, draw_command_buffer, "ui");
+                self.ui.record(base, draw_command_buffer, present_index);
+                self.profiler.end_pass(device, draw_command_buffer);
+            }
+        );
+
+        if let Some(report) = self.profiler.report() {
+            base.window.set_title(&format!("Tarsier | {report}"));
+        }
+
+        let present_info = vk::PresentInfoKHR {
+            wait_semaphore_count: 1,
+            p_wait_semaphores: &base.rendering_complete_semaphore,
+            swapchain_count: 1,
+            p_swapchains: &base.swapchain,
+            p_image_indices: &present_index,
+            ..Default::default()
+        };
+
+        match base.swapchain_loader.queue_present(base.present_queue, &present_info) {
+            Ok(false) => (),
+            Ok(true) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => base.swapchain_outdated = true,
+            Err(error) => panic!("Could not present swapchain image: {error}")
+        }
+    }
+
+    pub unsafe fn destroy(&mut self, base: &Base) {
+        self.destroy_framebuffers(base);
+        self.profiler.destroy(base);
+        self.globals.destroy(base);
+        self.ui.destroy(base);
+
+        base.device.destroy_render_pass(self.renderpass, None);
+    }
+}
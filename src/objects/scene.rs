@@ -0,0 +1,427 @@
+use std::collections::HashSet;
+use std::error::Error;
+
+use ash::vk;
+use nalgebra_glm as glm;
+
+use crate::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlphaMode {
+    Opaque,
+    Mask,
+    Blend
+}
+
+/// glTF metallic-roughness material, textures are indices into `Scene::textures`
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub name: Option<String>,
+
+    pub base_color_factor: glm::Vec4,
+    pub base_color_texture: Option<usize>,
+
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// Metalness in the blue channel, roughness in the green one
+    pub metallic_roughness_texture: Option<usize>,
+
+    pub normal_texture: Option<usize>,
+    pub normal_scale: f32,
+
+    pub occlusion_texture: Option<usize>,
+    pub occlusion_strength: f32,
+
+    pub emissive_factor: glm::Vec3,
+    pub emissive_texture: Option<usize>,
+
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool
+}
+
+pub struct Primitive {
+    pub model: Model,
+    /// Index into `Scene::materials`, `None` means the glTF default material
+    pub material: Option<usize>
+}
+
+pub struct Mesh {
+    pub name: Option<String>,
+    pub primitives: Vec<Primitive>
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Projection {
+    Perspective {
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        /// `None` means an infinite projection
+        zfar: Option<f32>
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SceneCamera {
+    pub name: Option<String>,
+    pub projection: Projection
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32
+    }
+}
+
+/// KHR_lights_punctual light, pointing down the -Z axis of its node
+#[derive(Clone, Debug)]
+pub struct SceneLight {
+    pub name: Option<String>,
+    pub kind: LightKind,
+    pub color: glm::Vec3,
+    pub intensity: f32,
+    pub range: Option<f32>
+}
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub name: Option<String>,
+    /// Relative to the parent node
+    pub transform: glm::Mat4,
+    pub children: Vec<usize>,
+
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub light: Option<usize>
+}
+
+/// A glTF scene: every list is indexed the same way as in the source document
+pub struct Scene {
+    pub nodes: Vec<Node>,
+    /// Root nodes of the default scene
+    pub roots: Vec<usize>,
+
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    /// One texture per glTF image, samplers are not imported
+    pub textures: Vec<Texture2D>,
+    pub cameras: Vec<SceneCamera>,
+    pub lights: Vec<SceneLight>
+}
+
+impl Scene {
+    /// Loads a `.gltf` (with embedded or external resources) or `.glb` file, uploading its images as textures
+    pub unsafe fn load_gltf(base: &Base, filepath: &str) -> Result<Self, Box<dyn Error>> {
+        let (document, buffers, images) = gltf::import(filepath)?;
+
+        // Color textures are stored in sRGB, everything else holds linear data
+        let mut srgb_images = HashSet::new();
+        for material in document.materials() {
+            if let Some(info) = material.pbr_metallic_roughness().base_color_texture() {
+                srgb_images.insert(info.texture().source().index());
+            }
+
+            if let Some(info) = material.emissive_texture() {
+                srgb_images.insert(info.texture().source().index());
+            }
+        }
+
+        let mut textures = vec![];
+        for (index, image) in images.iter().enumerate() {
+            let format = if srgb_images.contains(&index) {
+                vk::Format::R8G8B8A8_SRGB
+            } else {
+                vk::Format::R8G8B8A8_UNORM
+            };
+
+            match to_rgba8(image).and_then(|pixels| Texture2D::from_rgba8(base, image.width, image.height, &pixels, format)) {
+                Ok(texture) => textures.push(texture),
+                Err(error) => {
+                    for texture in textures.iter() {
+                        texture.data.destroy(base);
+                    }
+
+                    return Err(error);
+                }
+            }
+        }
+
+        let materials = document.materials().map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            let image_index = |texture: gltf::Texture| texture.source().index();
+
+            Material {
+                name: material.name().map(str::to_string),
+
+                base_color_factor: pbr.base_color_factor().into(),
+                base_color_texture: pbr.base_color_texture().map(|info| image_index(info.texture())),
+
+                metallic_factor: pbr.metallic_factor(),
+                roughness_factor: pbr.roughness_factor(),
+                metallic_roughness_texture: pbr.metallic_roughness_texture().map(|info| image_index(info.texture())),
+
+                normal_texture: material.normal_texture().map(|info| image_index(info.texture())),
+                normal_scale: material.normal_texture().map_or(1.0, |info| info.scale()),
+
+                occlusion_texture: material.occlusion_texture().map(|info| image_index(info.texture())),
+                occlusion_strength: material.occlusion_texture().map_or(1.0, |info| info.strength()),
+
+                emissive_factor: material.emissive_factor().into(),
+                emissive_texture: material.emissive_texture().map(|info| image_index(info.texture())),
+
+                alpha_mode: match material.alpha_mode() {
+                    gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                    gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+                    gltf::material::AlphaMode::Blend => AlphaMode::Blend
+                },
+                alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+                double_sided: material.double_sided()
+            }
+        }).collect();
+
+        let meshes = document.meshes().map(|mesh| Mesh {
+            name: mesh.name().map(str::to_string),
+            primitives: mesh
+                .primitives()
+                .filter_map(|primitive| {
+                    let model = load_primitive(&primitive, &buffers, &glm::identity())?;
+                    Some(Primitive { model, material: primitive.material().index() })
+                })
+                .collect()
+        }).collect();
+
+        let cameras = document.cameras().map(|camera| SceneCamera {
+            name: camera.name().map(str::to_string),
+            projection: match camera.projection() {
+                gltf::camera::Projection::Perspective(perspective) => Projection::Perspective {
+                    yfov: perspective.yfov(),
+                    aspect_ratio: perspective.aspect_ratio(),
+                    znear: perspective.znear(),
+                    zfar: perspective.zfar()
+                },
+                gltf::camera::Projection::Orthographic(orthographic) => Projection::Orthographic {
+                    xmag: orthographic.xmag(),
+                    ymag: orthographic.ymag(),
+                    znear: orthographic.znear(),
+                    zfar: orthographic.zfar()
+                }
+            }
+        }).collect();
+
+        let lights = document.lights().into_iter().flatten().map(|light| SceneLight {
+            name: light.name().map(str::to_string),
+            kind: match light.kind() {
+                gltf::khr_lights_punctual::Kind::Directional => LightKind::Directional,
+                gltf::khr_lights_punctual::Kind::Point => LightKind::Point,
+                gltf::khr_lights_punctual::Kind::Spot { inner_cone_angle, outer_cone_angle } => LightKind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle
+                }
+            },
+            color: light.color().into(),
+            intensity: light.intensity(),
+            range: light.range()
+        }).collect();
+
+        let nodes = document.nodes().map(|node| Node {
+            name: node.name().map(str::to_string),
+            transform: node.transform().matrix().into(),
+            children: node.children().map(|child| child.index()).collect(),
+
+            mesh: node.mesh().map(|mesh| mesh.index()),
+            camera: node.camera().map(|camera| camera.index()),
+            light: node.light().map(|light| light.index())
+        }).collect();
+
+        let roots = default_scene(&document)
+            .map(|scene| scene.nodes().map(|node| node.index()).collect())
+            .unwrap_or_default();
+
+        Ok(Scene {
+            nodes,
+            roots,
+
+            meshes,
+            materials,
+            textures,
+            cameras,
+            lights
+        })
+    }
+
+    /// World transform of every node, nodes outside the default scene keep the identity
+    pub fn world_transforms(&self) -> Vec<glm::Mat4> {
+        let mut transforms = vec![glm::Mat4::identity(); self.nodes.len()];
+        let mut stack: Vec<(usize, glm::Mat4)> = self.roots
+            .iter()
+            .map(|&root| (root, glm::Mat4::identity()))
+            .collect();
+
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            transforms[index] = parent * node.transform;
+
+            for &child in node.children.iter() {
+                stack.push((child, transforms[index]));
+            }
+        }
+
+        transforms
+    }
+
+    pub unsafe fn destroy(&self, base: &Base) {
+        for texture in self.textures.iter() {
+            texture.data.destroy(base);
+        }
+    }
+}
+
+impl Model {
+    /// Loads only the geometry of a glTF file, merging every mesh of the default scene with its node transform applied.
+    /// Each primitive becomes a submesh, its material is converted to an `ObjMaterial`, see `ObjMaterial::from_gltf`.
+    pub fn from_gltf(filepath: &str) -> Result<Self, Box<dyn Error>> {
+        let gltf::Gltf { document, blob } = gltf::Gltf::open(filepath)?;
+        let directory = std::path::Path::new(filepath).parent();
+        let buffers = gltf::import_buffers(&document, directory, blob)?;
+
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        let mut submeshes = vec![];
+
+        let mut stack: Vec<(gltf::Node, glm::Mat4)> = default_scene(&document)
+            .map(|scene| scene.nodes().map(|node| (node, glm::Mat4::identity())).collect())
+            .unwrap_or_default();
+
+        while let Some((node, parent)) = stack.pop() {
+            let transform = parent * glm::Mat4::from(node.transform().matrix());
+
+            for primitive in node.mesh().iter().flat_map(|mesh| mesh.primitives()) {
+                if let Some(part) = load_primitive(&primitive, &buffers, &transform) {
+                    let offset = vertices.len() as u32;
+
+                    submeshes.push(Submesh {
+                        first_index: indices.len() as u32,
+                        index_count: part.indices.len() as u32,
+                        material: primitive.material().index()
+                    });
+
+                    vertices.extend(part.vertices);
+                    indices.extend(part.indices.iter().map(|index| index + offset));
+                }
+            }
+
+            stack.extend(node.children().map(|child| (child, transform)));
+        }
+
+        let mut model = Model::new(vertices, indices);
+        model.submeshes = submeshes;
+        model.materials = document
+            .materials()
+            .map(|material| ObjMaterial::from_gltf(&material, directory.unwrap_or(std::path::Path::new(""))))
+            .collect();
+
+        Ok(model)
+    }
+}
+
+fn default_scene(document: &gltf::Document) -> Option<gltf::Scene<'_>> {
+    document.default_scene().or_else(|| document.scenes().next())
+}
+
+/// Converts a triangle primitive, other topologies are skipped
+fn load_primitive(primitive: &gltf::Primitive, buffers: &[gltf::buffer::Data], transform: &glm::Mat4) -> Option<Model> {
+    if primitive.mode() != gltf::mesh::Mode::Triangles {
+        return None;
+    }
+
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+    let positions: Vec<[f32; 3]> = reader.read_positions()?.collect();
+
+    let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(|normals| normals.collect());
+    let tangents: Option<Vec<[f32; 4]>> = reader.read_tangents().map(|tangents| tangents.collect());
+    let uvs: Option<Vec<[f32; 2]>> = reader.read_tex_coords(0).map(|uvs| uvs.into_f32().collect());
+    let colors: Option<Vec<[f32; 4]>> = reader.read_colors(0).map(|colors| colors.into_rgba_f32().collect());
+
+    let indices = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect()
+    };
+
+    let normal_matrix = glm::mat4_to_mat3(&glm::transpose(&glm::inverse(transform)));
+
+    let vertices = positions.iter().enumerate().map(|(index, position)| {
+        let mut vertex = Vertex::new(
+            glm::vec4_to_vec3(&(transform * glm::vec4(position[0], position[1], position[2], 1.0))),
+            uvs.as_ref().map_or(glm::vec2(0.0, 0.0), |uvs| uvs[index].into()),
+            normals.as_ref().map_or(glm::vec3(0.0, 0.0, 1.0), |normals| {
+                (normal_matrix * glm::Vec3::from(normals[index])).normalize()
+            }),
+            colors.as_ref().map_or(glm::vec4(1.0, 1.0, 1.0, 1.0), |colors| colors[index].into())
+        );
+
+        if let Some(tangents) = tangents.as_ref() {
+            let [x, y, z, w] = tangents[index];
+            let tangent = (glm::mat4_to_mat3(transform) * glm::vec3(x, y, z)).normalize();
+            vertex.tangent = glm::vec4(tangent.x, tangent.y, tangent.z, w);
+        }
+
+        vertex
+    }).collect();
+
+    let mut model = Model::new(vertices, indices);
+
+    if normals.is_none() {
+        model.compute_normals();
+    }
+
+    if tangents.is_none() {
+        model.compute_tangents();
+    }
+
+    Some(model)
+}
+
+fn to_rgba8(image: &gltf::image::Data) -> Result<Vec<u8>, Box<dyn Error>> {
+    use gltf::image::Format;
+
+    // 16 bit formats keep the most significant byte, stored second in little endian
+    let (channels, bytes_per_channel) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => return Ok(image.pixels.clone()),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        format => return Err(format!("Unsupported glTF image format {format:?}").into())
+    };
+
+    let pixel_size = channels * bytes_per_channel;
+    let rgba = image.pixels
+        .chunks_exact(pixel_size)
+        .flat_map(|pixel| {
+            let channel = |index: usize| pixel[index * bytes_per_channel + bytes_per_channel - 1];
+
+            match channels {
+                1 => [channel(0), channel(0), channel(0), 255],
+                2 => [channel(0), channel(1), 0, 255],
+                3 => [channel(0), channel(1), channel(2), 255],
+                _ => [channel(0), channel(1), channel(2), channel(3)]
+            }
+        })
+        .collect();
+
+    Ok(rgba)
+}
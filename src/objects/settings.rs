@@ -0,0 +1,71 @@
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+use ash::vk;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentMode {
+    Vsync,
+    Mailbox,
+    Immediate,
+    FifoRelaxed
+}
+
+impl PresentMode {
+    pub fn to_vk(self) -> vk::PresentModeKHR {
+        match self {
+            PresentMode::Vsync => vk::PresentModeKHR::FIFO,
+            PresentMode::Mailbox => vk::PresentModeKHR::MAILBOX,
+            PresentMode::Immediate => vk::PresentModeKHR::IMMEDIATE,
+            PresentMode::FifoRelaxed => vk::PresentModeKHR::FIFO_RELAXED
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub window_width: u32,
+    pub window_height: u32,
+
+    /// Requested MSAA sample count, clamped to what the device supports for both color and depth attachments
+    pub samples: vk::SampleCountFlags,
+
+    /// Picks a depth format with a stencil component
+    pub stencil: bool,
+    pub stencil_load_op: vk::AttachmentLoadOp,
+    pub stencil_store_op: vk::AttachmentStoreOp,
+
+    /// Maps the near plane to 1.0 and the far plane to 0.0, see `Base::projection`
+    pub reversed_z: bool,
+
+    /// Falls back to `PresentMode::Vsync` when the surface doesn't support it, see `Base::set_present_mode`
+    pub present_mode: PresentMode,
+    /// Frames per second cap, the render loop sleeps between frames instead of spinning
+    pub max_frame_rate: Option<NonZeroU32>,
+
+    /// Directory keeping compiled pipelines between runs, one file per GPU. Off by default, since the library
+    /// can't tell which directory is the app's to write to.
+    pub pipeline_cache: Option<PathBuf>
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            window_width: 1920,
+            window_height: 1080,
+
+            samples: vk::SampleCountFlags::TYPE_1,
+
+            stencil: false,
+            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+
+            reversed_z: false,
+
+            present_mode: PresentMode::Mailbox,
+            max_frame_rate: None,
+
+            pipeline_cache: None
+        }
+    }
+}
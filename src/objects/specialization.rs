@@ -0,0 +1,173 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use ash::vk;
+
+use crate::*;
+
+/// Current values of the specialization constants of a pipeline, shared by all its stages since constants are
+/// matched by ID. Bools get checkboxes in `ui` and numbers drag values, the pipeline has to be rebuilt with the
+/// new `info` whenever something changed.
+#[derive(Clone)]
+pub struct SpecializationConstants {
+    /// Where the Save and Load buttons of `ui` keep the preset, the buttons are hidden while `None`
+    pub preset_path: Option<PathBuf>,
+
+    constants: Vec<SpecializationConstant>,
+    /// Raw bits, one word per constant in the same order
+    values: Vec<u32>,
+    map_entries: Vec<vk::SpecializationMapEntry>
+}
+
+impl SpecializationConstants {
+    /// Constants declared by several stages with the same ID keep the first declaration
+    pub fn new(reflections: &[&ShaderReflection]) -> Self {
+        let mut constants: Vec<SpecializationConstant> = vec![];
+
+        for constant in reflections.iter().flat_map(|reflection| reflection.specialization_constants.iter()) {
+            if !constants.iter().any(|existing| existing.id == constant.id) {
+                constants.push(constant.clone());
+            }
+        }
+
+        constants.sort_by_key(|constant| constant.id);
+
+        let map_entries = constants
+            .iter()
+            .enumerate()
+            .map(|(index, constant)| vk::SpecializationMapEntry {
+                constant_id: constant.id,
+                offset: (index * 4) as u32,
+                size: 4
+            })
+            .collect();
+
+        SpecializationConstants {
+            preset_path: None,
+
+            values: constants.iter().map(|constant| constant.default).collect(),
+            constants,
+            map_entries
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constants.is_empty()
+    }
+
+    /// Borrows `self`, so it has to outlive the pipeline creation it's passed to
+    pub fn info(&self) -> vk::SpecializationInfo {
+        let data = unsafe { std::slice::from_raw_parts(self.values.as_ptr().cast::<u8>(), self.values.len() * 4) };
+
+        vk::SpecializationInfo::builder()
+            .map_entries(&self.map_entries)
+            .data(data)
+            .build()
+    }
+
+    pub fn value(&self, name: &str) -> Option<f64> {
+        self.position(name).map(|index| self.get(index))
+    }
+
+    /// Returns whether a constant with that name exists, bools are true for anything but zero
+    pub fn set_value(&mut self, name: &str, value: f64) -> bool {
+        match self.position(name) {
+            Some(index) => {
+                self.set(index, value);
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Returns whether anything changed, in which case the pipeline needs rebuilding
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        for index in 0..self.constants.len() {
+            let name = &self.constants[index].name;
+            let value = &mut self.values[index];
+
+            changed |= match self.constants[index].kind {
+                ScalarKind::Bool => {
+                    let mut checked = *value != 0;
+                    let edited = ui.checkbox(&mut checked, name).changed();
+                    *value = checked as u32;
+                    edited
+                },
+                ScalarKind::Int { signed: true } => ui.horizontal(|ui| {
+                    let mut number = *value as i32;
+                    let edited = ui.add(egui::DragValue::new(&mut number)).changed();
+                    *value = number as u32;
+                    ui.label(name);
+                    edited
+                }).inner,
+                ScalarKind::Int { signed: false } => ui.horizontal(|ui| {
+                    let edited = ui.add(egui::DragValue::new(value)).changed();
+                    ui.label(name);
+                    edited
+                }).inner,
+                ScalarKind::Float => ui.horizontal(|ui| {
+                    let mut number = f32::from_bits(*value);
+                    let edited = ui.add(egui::DragValue::new(&mut number).speed(0.01)).changed();
+                    *value = number.to_bits();
+                    ui.label(name);
+                    edited
+                }).inner
+            };
+        }
+
+        let path = self.preset_path.clone();
+        changed |= preset_ui(ui, self, path.as_deref());
+
+        changed
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.constants.iter().position(|constant| constant.name == name)
+    }
+
+    fn get(&self, index: usize) -> f64 {
+        let value = self.values[index];
+
+        match self.constants[index].kind {
+            ScalarKind::Bool | ScalarKind::Int { signed: false } => value as f64,
+            ScalarKind::Int { signed: true } => value as i32 as f64,
+            ScalarKind::Float => f32::from_bits(value) as f64
+        }
+    }
+
+    fn set(&mut self, index: usize, value: f64) {
+        self.values[index] = match self.constants[index].kind {
+            ScalarKind::Bool => (value != 0.0) as u32,
+            ScalarKind::Int { signed: false } => value.round().max(0.0) as u32,
+            ScalarKind::Int { signed: true } => value.round() as i32 as u32,
+            ScalarKind::Float => (value as f32).to_bits()
+        };
+    }
+}
+
+impl Preset for SpecializationConstants {
+    fn entries(&self) -> Vec<(String, Vec<f64>)> {
+        (0..self.constants.len()).map(|index| (self.constants[index].name.clone(), vec![self.get(index)])).collect()
+    }
+
+    fn set_entry(&mut self, name: &str, values: &[f64]) -> Result<(), Box<dyn Error>> {
+        let Some(index) = self.position(name) else {
+            return Ok(());
+        };
+
+        match values {
+            [value] => {
+                self.set(index, *value);
+                Ok(())
+            },
+            _ => Err(format!("Expected a single value for {name}").into())
+        }
+    }
+
+    /// Back to the values compiled into the shaders
+    fn reset(&mut self) {
+        self.values = self.constants.iter().map(|constant| constant.default).collect();
+    }
+}
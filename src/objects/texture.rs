@@ -27,8 +27,8 @@ impl Texture {
         base.device.destroy_image_view(self.view, None);
         base.device.destroy_image(self.image, None);
 
-        if self.sampler.is_some() {
-            base.device.destroy_sampler(self.sampler.unwrap(), None);
+        if let Some(sampler) = self.sampler {
+            base.device.destroy_sampler(sampler, None);
         }
 
         base.device.free_memory(self.memory, None);
@@ -51,13 +51,24 @@ impl Texture2D {
         // Read image and get info and data
         let image = Reader::open(filename)?.decode()?.to_rgba8();
         let (width, height) = image.dimensions();
+
+        Self::from_rgba8(base, width, height, &image.into_raw(), vk::Format::R8G8B8A8_UNORM)
+    }
+
+    /// Uploads already decoded RGBA8 pixels, `format` picks between the UNORM and SRGB interpretation
+    pub unsafe fn from_rgba8(
+        base: &Base,
+        width: u32,
+        height: u32,
+        image_data: &[u8],
+        format: vk::Format
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let image_extent = vk::Extent2D { width, height };
         let mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
-        let image_data = image.into_raw();
 
         let staging_buffer = Buffer::new(
             base,
-            (std::mem::size_of::<u8>() * image_data.len()) as u64,
+            std::mem::size_of_val(image_data) as u64,
             vk::BufferUsageFlags::TRANSFER_SRC,
             vk::SharingMode::EXCLUSIVE,
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
@@ -71,11 +82,11 @@ impl Texture2D {
         // Create texture image and buffer
         let texture_create_info = vk::ImageCreateInfo::builder()
             .image_type(vk::ImageType::TYPE_2D)
-            .format(vk::Format::R8G8B8A8_UNORM) // TODO: maybe maybe expose it as parameter
+            .format(format)
             .extent(image_extent.into())
             .mip_levels(1) // TODO: implement mipmapping
             .array_layers(1)
-            .samples(vk::SampleCountFlags::TYPE_1) // TODO: implement multisampling
+            .samples(vk::SampleCountFlags::TYPE_1)
             .tiling(vk::ImageTiling::OPTIMAL)
             .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
             .sharing_mode(vk::SharingMode::EXCLUSIVE)
@@ -201,12 +212,6 @@ impl Texture2D {
 
         let tex_image_view = base.device.create_image_view(&tex_image_view_info, None).unwrap();
 
-        let descriptor = vk::DescriptorImageInfo { 
-            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
-            image_view: tex_image_view,
-            sampler
-        };
-
         Ok(Texture2D {
             data: Texture {
                 image: texture_image,
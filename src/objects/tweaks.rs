@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+
+use crate::*;
+
+const COMPONENT_NAMES: [&str; 4] = ["x", "y", "z", "w"];
+
+/// What follows a member declaration in the shader source, e.g. `float roughness; // @range(0, 1) @default(0.5)`.
+/// Supported: `@range(min, max)`, `@default(x, ...)`, `@step(s)`, `@color`, `@bool` and `@hidden`.
+/// Floats without a range get a 0 to 1 slider that doesn't clamp.
+#[derive(Clone, Debug, Default)]
+struct Annotation {
+    range: Option<(f64, f64)>,
+    default: Vec<f64>,
+    step: Option<f64>,
+    color: bool,
+    checkbox: bool,
+    hidden: bool
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Control {
+    /// Values are only kept within `min..=max` when it comes from a `@range`
+    Slider { components: usize, min: f64, max: f64, clamped: bool },
+    Color { alpha: bool },
+    Checkbox,
+    Stepper { components: usize, signed: bool, min: f64, max: f64, step: f64 }
+}
+
+#[derive(Clone, Debug)]
+struct Tweak {
+    name: String,
+    offset: usize,
+    control: Control,
+    default: Vec<f64>
+}
+
+impl Tweak {
+    fn components(&self) -> usize {
+        match self.control {
+            Control::Slider { components, .. } | Control::Stepper { components, .. } => components,
+            Control::Color { alpha } => if alpha { 4 } else { 3 },
+            Control::Checkbox => 1
+        }
+    }
+
+    fn is_float(&self) -> bool {
+        matches!(self.control, Control::Slider { .. } | Control::Color { .. })
+    }
+}
+
+/// Live controls for the members of a reflected uniform block, holding a copy of the block contents.
+/// Floats and float vectors get sliders (or color pickers with `@color`), integers get steppers and bools checkboxes.
+/// Matrices, arrays and nested structs are left alone.
+pub struct UniformTweaks {
+    pub name: String,
+    /// Where the Save and Load buttons of `ui` keep the preset, the buttons are hidden while `None`
+    pub preset_path: Option<PathBuf>,
+
+    tweaks: Vec<Tweak>,
+    data: Vec<u8>
+}
+
+impl UniformTweaks {
+    /// `source` is the GLSL the block was compiled from, only read for the annotations inside the block declaration
+    pub fn new(block: &UniformBlock, source: &str) -> Self {
+        let annotations = parse_annotations(source, &block.name);
+
+        let tweaks = block.members.iter().filter_map(|member| {
+            let (declared_type, annotation) = annotations.get(&member.name).cloned().unwrap_or_default();
+
+            if annotation.hidden {
+                return None;
+            }
+
+            let (min, max) = annotation.range.unwrap_or((0.0, 1.0));
+            let clamped = annotation.range.is_some();
+            let step = annotation.step.unwrap_or(1.0);
+
+            let control = match member.ty {
+                UniformType::Scalar(ScalarKind::Float) => Control::Slider { components: 1, min, max, clamped },
+                UniformType::Vector(ScalarKind::Float, count @ (3 | 4)) if annotation.color => Control::Color { alpha: count == 4 },
+                UniformType::Vector(ScalarKind::Float, count) => Control::Slider { components: count as usize, min, max, clamped },
+
+                // GLSL bools are turned into 32 bit integers inside blocks
+                UniformType::Scalar(ScalarKind::Bool) => Control::Checkbox,
+                UniformType::Scalar(ScalarKind::Int { .. }) if annotation.checkbox || declared_type == "bool" => Control::Checkbox,
+
+                UniformType::Scalar(ScalarKind::Int { signed }) | UniformType::Vector(ScalarKind::Int { signed }, _) => {
+                    let components = match member.ty {
+                        UniformType::Vector(_, count) => count as usize,
+                        _ => 1
+                    };
+
+                    let (min, max) = annotation.range.unwrap_or((if signed { i32::MIN as f64 } else { 0.0 }, i32::MAX as f64));
+                    Control::Stepper { components, signed, min, max, step }
+                },
+
+                _ => return None
+            };
+
+            let mut tweak = Tweak {
+                name: member.name.clone(),
+                offset: member.offset as usize,
+                control,
+                default: vec![]
+            };
+
+            // A single default value applies to every component, colors start white
+            tweak.default = (0..tweak.components())
+                .map(|component| match annotation.default.len() {
+                    0 if matches!(control, Control::Color { .. }) => 1.0,
+                    0 => 0.0,
+                    1 => annotation.default[0],
+                    _ => annotation.default.get(component).cloned().unwrap_or(0.0)
+                })
+                .collect();
+
+            Some(tweak)
+        }).collect();
+
+        let mut tweaks = UniformTweaks {
+            name: block.name.clone(),
+            preset_path: None,
+
+            tweaks,
+            data: vec![0; block.size as usize]
+        };
+
+        tweaks.reset();
+        tweaks
+    }
+
+    /// Block contents laid out like the shader expects them, untweaked members are zero
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Copies the tweaked members into a mapped buffer holding the block, leaving the other members untouched.
+    /// Meant to be called every frame, after the app wrote its own members.
+    pub unsafe fn write(&self, buffer: &Buffer) {
+        let ptr = buffer.ptr.expect("Uniform buffer is not mapped") as *mut u8;
+
+        for tweak in self.tweaks.iter() {
+            let size = tweak.components() * 4;
+            std::ptr::copy_nonoverlapping(self.data[tweak.offset..].as_ptr(), ptr.add(tweak.offset), size);
+        }
+    }
+
+    /// Returns whether anything changed
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        for index in 0..self.tweaks.len() {
+            let tweak = self.tweaks[index].clone();
+            let mut values = self.values(index);
+
+            let edited = match tweak.control {
+                Control::Slider { components, min, max, clamped } => {
+                    let mut edited = false;
+
+                    for (component, value) in values.iter_mut().enumerate() {
+                        let text = if components == 1 { tweak.name.clone() } else { format!("{}.{}", tweak.name, COMPONENT_NAMES[component]) };
+                        edited |= ui.add(egui::Slider::new(value, min..=max).clamp_to_range(clamped).text(text)).changed();
+                    }
+
+                    edited
+                },
+                Control::Color { alpha } => ui.horizontal(|ui| {
+                    let edited = if alpha {
+                        let mut rgba = [values[0] as f32, values[1] as f32, values[2] as f32, values[3] as f32];
+                        let edited = ui.color_edit_button_rgba_unmultiplied(&mut rgba).changed();
+                        values = rgba.iter().map(|&value| value as f64).collect();
+                        edited
+                    } else {
+                        let mut rgb = [values[0] as f32, values[1] as f32, values[2] as f32];
+                        let edited = ui.color_edit_button_rgb(&mut rgb).changed();
+                        values = rgb.iter().map(|&value| value as f64).collect();
+                        edited
+                    };
+
+                    ui.label(&tweak.name);
+                    edited
+                }).inner,
+                Control::Checkbox => {
+                    let mut checked = values[0] != 0.0;
+                    let edited = ui.checkbox(&mut checked, &tweak.name).changed();
+                    values[0] = if checked { 1.0 } else { 0.0 };
+                    edited
+                },
+                Control::Stepper { min, max, step, .. } => ui.horizontal(|ui| {
+                    let mut edited = false;
+
+                    for value in values.iter_mut() {
+                        edited |= ui.add(egui::DragValue::new(value).speed(step).clamp_range(min..=max)).changed();
+                    }
+
+                    ui.label(&tweak.name);
+                    edited
+                }).inner
+            };
+
+            if edited {
+                self.set_values(index, &values);
+                changed = true;
+            }
+        }
+
+        let path = self.preset_path.clone();
+        changed |= preset_ui(ui, self, path.as_deref());
+
+        changed
+    }
+
+    fn values(&self, index: usize) -> Vec<f64> {
+        let tweak = &self.tweaks[index];
+
+        (0..tweak.components()).map(|component| {
+            let offset = tweak.offset + component * 4;
+            let bytes: [u8; 4] = self.data[offset..offset + 4].try_into().unwrap();
+
+            match tweak.control {
+                _ if tweak.is_float() => f32::from_le_bytes(bytes) as f64,
+                Control::Stepper { signed: true, .. } => i32::from_le_bytes(bytes) as f64,
+                _ => u32::from_le_bytes(bytes) as f64
+            }
+        }).collect()
+    }
+
+    fn set_values(&mut self, index: usize, values: &[f64]) {
+        let tweak = &self.tweaks[index];
+
+        for (component, &value) in values.iter().enumerate().take(tweak.components()) {
+            let bytes = match tweak.control {
+                Control::Slider { min, max, clamped: true, .. } => (value.clamp(min.min(max), max.max(min)) as f32).to_le_bytes(),
+                Control::Slider { clamped: false, .. } | Control::Color { .. } => (value as f32).to_le_bytes(),
+                Control::Checkbox => (value as u32).to_le_bytes(),
+                Control::Stepper { signed: true, min, max, .. } => (value.clamp(min, max).round() as i32).to_le_bytes(),
+                Control::Stepper { signed: false, min, max, .. } => (value.clamp(min.max(0.0), max).round() as u32).to_le_bytes()
+            };
+
+            let offset = tweak.offset + component * 4;
+            self.data[offset..offset + 4].copy_from_slice(&bytes);
+        }
+    }
+}
+
+impl Preset for UniformTweaks {
+    fn entries(&self) -> Vec<(String, Vec<f64>)> {
+        (0..self.tweaks.len()).map(|index| (self.tweaks[index].name.clone(), self.values(index))).collect()
+    }
+
+    fn set_entry(&mut self, name: &str, values: &[f64]) -> Result<(), Box<dyn Error>> {
+        let Some(index) = self.tweaks.iter().position(|tweak| tweak.name == name) else {
+            return Ok(());
+        };
+
+        if values.len() != self.tweaks[index].components() {
+            return Err(format!("Wrong number of values for {name}").into());
+        }
+
+        self.set_values(index, values);
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        for index in 0..self.tweaks.len() {
+            let default = self.tweaks[index].default.clone();
+            self.set_values(index, &default);
+        }
+    }
+}
+
+/// Member name to (declared type, annotation), for every declaration inside `uniform <block_name> { ... }` followed
+/// by a comment with at least one `@`. Members are expected one per line, between the lines with the braces.
+fn parse_annotations(source: &str, block_name: &str) -> HashMap<String, (String, Annotation)> {
+    let mut annotations = HashMap::new();
+    let mut in_block = false;
+
+    for line in source.lines() {
+        let (code, comment) = line.split_once("//").unwrap_or((line, ""));
+
+        if !in_block {
+            let mut words = code.split(|c: char| c.is_whitespace() || c == '{').filter(|word| !word.is_empty());
+            in_block = words.any(|word| word == "uniform") && words.next() == Some(block_name);
+            continue;
+        }
+
+        if code.contains('}') {
+            break;
+        }
+
+        let tokens: Vec<&str> = code.trim().trim_end_matches(';').split_whitespace().collect();
+
+        if tokens.len() < 2 || !comment.contains('@') {
+            continue;
+        }
+
+        let name = tokens[tokens.len() - 1].split('[').next().unwrap_or_default();
+        let declared_type = tokens[tokens.len() - 2];
+
+        let mut annotation = Annotation::default();
+
+        for tag in comment.split('@').skip(1) {
+            let tag_name: String = tag.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+            let arguments: Vec<f64> = tag[tag_name.len()..]
+                .trim_start()
+                .strip_prefix('(')
+                .and_then(|rest| rest.split_once(')'))
+                .map(|(arguments, _)| arguments.split(',').filter_map(|argument| argument.trim().parse().ok()).collect())
+                .unwrap_or_default();
+
+            match tag_name.as_str() {
+                "range" if arguments.len() == 2 => annotation.range = Some((arguments[0], arguments[1])),
+                "default" => annotation.default = arguments,
+                "step" => annotation.step = arguments.first().cloned(),
+                "color" => annotation.color = true,
+                "bool" => annotation.checkbox = true,
+                "hidden" => annotation.hidden = true,
+                _ => ()
+            }
+        }
+
+        annotations.insert(name.to_string(), (declared_type.to_string(), annotation));
+    }
+
+    annotations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "
+layout (set = 1, binding = 0) uniform First {
+    float scale; // @range(1, 4) @default(2)
+    float offset;
+} first;
+
+layout (set = 1, binding = 1) uniform Second
+{
+    float scale; // @range(0, 100) @default(50)
+} second;
+";
+
+    fn block(name: &str, members: &[&str]) -> UniformBlock {
+        UniformBlock {
+            name: name.to_string(),
+            set: 1,
+            binding: 0,
+            size: members.len() as u32 * 4,
+            members: members
+                .iter()
+                .enumerate()
+                .map(|(index, name)| UniformMember {
+                    name: name.to_string(),
+                    offset: index as u32 * 4,
+                    size: 4,
+                    ty: UniformType::Scalar(ScalarKind::Float)
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn annotations_per_block() {
+        let first = parse_annotations(SOURCE, "First");
+        assert_eq!(first["scale"].1.range, Some((1.0, 4.0)));
+        assert!(!first.contains_key("offset"));
+
+        let second = parse_annotations(SOURCE, "Second");
+        assert_eq!(second["scale"].1.range, Some((0.0, 100.0)));
+        assert_eq!(second["scale"].1.default, vec![50.0]);
+
+        assert!(parse_annotations(SOURCE, "Third").is_empty());
+    }
+
+    #[test]
+    fn clamp_only_ranged_members() {
+        let mut tweaks = UniformTweaks::new(&block("First", &["scale", "offset"]), SOURCE);
+        assert_eq!(tweaks.entries(), vec![("scale".to_string(), vec![2.0]), ("offset".to_string(), vec![0.0])]);
+
+        tweaks.set_entry("scale", &[10.0]).unwrap();
+        tweaks.set_entry("offset", &[-3.5]).unwrap();
+        assert_eq!(tweaks.entries(), vec![("scale".to_string(), vec![4.0]), ("offset".to_string(), vec![-3.5])]);
+    }
+}
@@ -0,0 +1,692 @@
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::io::Cursor;
+
+use ash::util::read_spv;
+use ash::vk;
+use egui::epaint::{ClippedPrimitive, ImageDelta, Primitive};
+use egui::{Color32, ImageData, TextureFilter, TextureId};
+use winit::event::VirtualKeyCode;
+
+use crate::*;
+
+const UI_VERTEX_SHADER: &[u8] = include_bytes!("../../shaders/builtin/ui_vert.spv");
+const UI_FRAGMENT_SHADER: &[u8] = include_bytes!("../../shaders/builtin/ui_frag.spv");
+
+/// Textures alive at once, egui itself only needs the font atlas
+const MAX_UI_TEXTURES: u32 = 64;
+
+/// Mirrors the push constants of `shaders/ui.vert`
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct UiPushConstants {
+    screen_size: [f32; 2],
+    srgb_framebuffer: u32
+}
+
+struct UiTexture {
+    texture: Texture2D,
+    descriptor_set: vk::DescriptorSet,
+
+    /// Kept on the CPU so partial updates can rebuild the whole image
+    pixels: Vec<Color32>,
+    size: [usize; 2]
+}
+
+/// egui layer drawn in its own render pass after the main one, straight on the swapchain image.
+/// Built-in window with frame timings, device and playback info, apps add their own through `App::ui`. F1 toggles it.
+pub struct UiOverlay {
+    pub visible: bool,
+
+    renderpass: vk::RenderPass,
+    framebuffers: Vec<vk::Framebuffer>,
+
+    set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    /// Indexed by `TextureFilter`
+    samplers: [vk::Sampler; 2],
+
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+
+    textures: HashMap<TextureId, UiTexture>,
+    primitives: Vec<ClippedPrimitive>,
+    pixels_per_point: f32,
+    srgb_framebuffer: bool
+}
+
+impl UiOverlay {
+    pub unsafe fn new(base: &Base) -> Self {
+        // ================================================================
+        // RENDERPASS
+        // ================================================================
+
+        // Picks up the image where the main pass left it, and hands it over for presenting
+        let attachments = [vk::AttachmentDescription {
+            format: base.surface_format.format,
+            samples: vk::SampleCountFlags::TYPE_1,
+            load_op: vk::AttachmentLoadOp::LOAD,
+            store_op: vk::AttachmentStoreOp::STORE,
+            initial_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            ..Default::default()
+        }];
+
+        let color_attachment_refs = [vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+        }];
+
+        let dependencies = [vk::SubpassDependency {
+            src_subpass: vk::SUBPASS_EXTERNAL,
+            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ..Default::default()
+        }];
+
+        let subpass = vk::SubpassDescription::builder()
+            .color_attachments(&color_attachment_refs)
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .build();
+
+        let renderpass_create_info = vk::RenderPassCreateInfo::builder()
+            .attachments(&attachments)
+            .subpasses(std::slice::from_ref(&subpass))
+            .dependencies(&dependencies)
+            .build();
+
+        let renderpass = base.device.create_render_pass(&renderpass_create_info, None).unwrap();
+
+        // ================================================================
+        // DESCRIPTORS
+        // ================================================================
+
+        // The image and its sampler are separate bindings, see `shaders/ui.frag`
+        let layout_bindings = [
+            vk::DescriptorSetLayoutBinding {
+                descriptor_type: vk::DescriptorType::SAMPLED_IMAGE,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            }
+        ];
+
+        let set_layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&layout_bindings).build();
+        let set_layout = base.device.create_descriptor_set_layout(&set_layout_info, None).unwrap();
+
+        let pool_sizes = [
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::SAMPLED_IMAGE,
+                descriptor_count: MAX_UI_TEXTURES
+            },
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::SAMPLER,
+                descriptor_count: MAX_UI_TEXTURES
+            }
+        ];
+
+        // Sets are freed one by one as egui frees its textures
+        let descriptor_pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .pool_sizes(&pool_sizes)
+            .max_sets(MAX_UI_TEXTURES)
+            .build();
+        let descriptor_pool = base.device.create_descriptor_pool(&descriptor_pool_info, None).unwrap();
+
+        let samplers = [TextureFilter::Nearest, TextureFilter::Linear].map(|filter| {
+            let filter = match filter {
+                TextureFilter::Nearest => vk::Filter::NEAREST,
+                TextureFilter::Linear => vk::Filter::LINEAR
+            };
+
+            let sampler_info = vk::SamplerCreateInfo {
+                mag_filter: filter,
+                min_filter: filter,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_anisotropy: 1.0,
+                ..Default::default()
+            };
+
+            base.device.create_sampler(&sampler_info, None).unwrap()
+        });
+
+        // ================================================================
+        // PIPELINE
+        // ================================================================
+
+        let push_constant_ranges = [push_constant_range::<UiPushConstants>(vk::ShaderStageFlags::VERTEX, 0)];
+
+        let set_layouts = [set_layout];
+        let layout_create_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(&push_constant_ranges)
+            .build();
+        let pipeline_layout = base.device.create_pipeline_layout(&layout_create_info, None).unwrap();
+
+        let vertex_code = read_spv(&mut Cursor::new(UI_VERTEX_SHADER)).expect("Failed to read UI vertex shader");
+        let vertex_shader_info = vk::ShaderModuleCreateInfo::builder().code(&vertex_code).build();
+        let vertex_shader_module = base.device.create_shader_module(&vertex_shader_info, None).expect("UI vertex shader module error");
+
+        let frag_code = read_spv(&mut Cursor::new(UI_FRAGMENT_SHADER)).expect("Failed to read UI fragment shader");
+        let frag_shader_info = vk::ShaderModuleCreateInfo::builder().code(&frag_code).build();
+        let frag_shader_module = base.device.create_shader_module(&frag_shader_info, None).expect("UI fragment shader module error");
+
+        let shader_entry_name = c"main";
+        let shader_stage_create_infos = [
+            vk::PipelineShaderStageCreateInfo {
+                module: vertex_shader_module,
+                p_name: shader_entry_name.as_ptr(),
+                stage: vk::ShaderStageFlags::VERTEX,
+                ..Default::default()
+            },
+            vk::PipelineShaderStageCreateInfo {
+                module: frag_shader_module,
+                p_name: shader_entry_name.as_ptr(),
+                stage: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            }
+        ];
+
+        // Matches `egui::epaint::Vertex`: position and UV in points, then sRGB color
+        let vertex_binding_descriptions = [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: std::mem::size_of::<egui::epaint::Vertex>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX
+        }];
+
+        let vertex_attribute_descriptions = [
+            vk::VertexInputAttributeDescription { location: 0, binding: 0, format: vk::Format::R32G32_SFLOAT, offset: 0 },
+            vk::VertexInputAttributeDescription { location: 1, binding: 0, format: vk::Format::R32G32_SFLOAT, offset: 8 },
+            vk::VertexInputAttributeDescription { location: 2, binding: 0, format: vk::Format::R8G8B8A8_UNORM, offset: 16 }
+        ];
+
+        let vertex_input_state_info = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_attribute_descriptions(&vertex_attribute_descriptions)
+            .vertex_binding_descriptions(&vertex_binding_descriptions)
+            .build();
+
+        let vertex_input_assembly_state_info = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .build();
+
+        // Both set when recording
+        let viewport_state_info = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1)
+            .build();
+
+        // egui outputs premultiplied alpha, the pass has no depth attachment
+        let pipeline_state = PipelineState::premultiplied().depth_test(false);
+        let rasterization_info = pipeline_state.rasterization_info(base);
+
+        let multisample_state_info = vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1)
+            .build();
+
+        let depth_state_info = vk::PipelineDepthStencilStateCreateInfo::default();
+
+        let color_blend_attachment_states = [pipeline_state.color_blend_attachment()];
+
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(&color_blend_attachment_states)
+            .build();
+
+        let dynamic_state = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state_info = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_state).build();
+
+        let graphics_pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&shader_stage_create_infos)
+            .vertex_input_state(&vertex_input_state_info)
+            .input_assembly_state(&vertex_input_assembly_state_info)
+            .viewport_state(&viewport_state_info)
+            .rasterization_state(&rasterization_info)
+            .multisample_state(&multisample_state_info)
+            .depth_stencil_state(&depth_state_info)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state_info)
+            .layout(pipeline_layout)
+            .render_pass(renderpass)
+            .build();
+
+        let pipeline = base
+            .device
+            .create_graphics_pipelines(base.pipeline_cache, &[graphics_pipeline_info], None)
+            .unwrap()[0];
+
+        base.device.destroy_shader_module(vertex_shader_module, None);
+        base.device.destroy_shader_module(frag_shader_module, None);
+
+        let mut overlay = UiOverlay {
+            visible: true,
+
+            renderpass,
+            framebuffers: vec![],
+
+            set_layout,
+            descriptor_pool,
+            pipeline_layout,
+            pipeline,
+            samplers,
+
+            vertex_buffer: None,
+            index_buffer: None,
+
+            textures: HashMap::new(),
+            primitives: vec![],
+            pixels_per_point: 1.0,
+            srgb_framebuffer: is_srgb(base.surface_format.format)
+        };
+
+        overlay.create_framebuffers(base);
+        overlay
+    }
+
+    /// Rebuilds the framebuffers after `Base::recreate_swapchain`
+    pub unsafe fn resize(&mut self, base: &Base) {
+        self.destroy_framebuffers(base);
+        self.create_framebuffers(base);
+    }
+
+    unsafe fn create_framebuffers(&mut self, base: &Base) {
+        self.framebuffers = base
+            .present_image_views
+            .iter()
+            .map(|&present_image_view| {
+                let framebuffer_attachments = [present_image_view];
+
+                let framebuffer_create_info = vk::FramebufferCreateInfo::builder()
+                    .render_pass(self.renderpass)
+                    .attachments(&framebuffer_attachments)
+                    .width(base.surface_resolution.width)
+                    .height(base.surface_resolution.height)
+                    .layers(1)
+                    .build();
+
+                base.device.create_framebuffer(&framebuffer_create_info, None).unwrap()
+            })
+            .collect();
+    }
+
+    unsafe fn destroy_framebuffers(&mut self, base: &Base) {
+        for &framebuffer in self.framebuffers.iter() {
+            base.device.destroy_framebuffer(framebuffer, None);
+        }
+    }
+
+    /// Runs a UI frame with the built-in window and whatever `build` adds, then uploads the textures it needs.
+    /// The result is drawn by the next `Renderer::draw_frame`.
+    pub unsafe fn run<F: FnOnce(&egui::Context)>(&mut self, base: &mut Base, frame: &FrameContext, profiler: &Profiler, build: F) {
+        if frame.input.key_pressed(VirtualKeyCode::F1) && !frame.input.ui_wants_keyboard {
+            self.visible = !self.visible;
+        }
+
+        // While hidden, empty frames still consume the queued events, so none are replayed once the overlay is
+        // shown again, and nothing keeps the pointer or keyboard
+        let visible = self.visible;
+        let context = base.ui_context.clone();
+        let raw_input = base.ui_input.take_egui_input(&base.window);
+
+        let output = context.run(raw_input, |context| {
+            if visible {
+                stats_window(context, base, frame, profiler);
+                build(context);
+            }
+        });
+
+        base.ui_input.handle_platform_output(&base.window, &context, output.platform_output);
+
+        // One wait covers every texture replaced or freed this frame, the previous frame may still sample them
+        let delta = &output.textures_delta;
+        if delta.set.iter().map(|(id, _)| id).chain(delta.free.iter()).any(|id| self.textures.contains_key(id)) {
+            base.device.device_wait_idle().unwrap();
+        }
+
+        for (id, delta) in output.textures_delta.set {
+            self.set_texture(base, id, delta);
+        }
+
+        // Freed textures are no longer referenced by the shapes of this frame
+        for id in output.textures_delta.free {
+            if let Some(texture) = self.textures.remove(&id) {
+                self.destroy_texture(base, &texture);
+            }
+        }
+
+        self.primitives = if visible { context.tessellate(output.shapes) } else { vec![] };
+        self.pixels_per_point = context.pixels_per_point();
+    }
+
+    /// Textures rarely change after the first frame (only when the font atlas grows), so they are simply rebuilt.
+    /// The GPU must be done with the texture being replaced.
+    unsafe fn set_texture(&mut self, base: &Base, id: TextureId, delta: ImageDelta) {
+        let (size, pixels): ([usize; 2], Vec<Color32>) = match &delta.image {
+            ImageData::Color(image) => (image.size, image.pixels.clone()),
+            ImageData::Font(image) => (image.size, image.srgba_pixels(None).collect())
+        };
+
+        let previous = self.textures.remove(&id);
+
+        let (size, pixels) = match (delta.pos, &previous) {
+            (Some([x, y]), Some(previous)) => {
+                let mut full = previous.pixels.clone();
+
+                for row in 0..size[1] {
+                    let start = (y + row) * previous.size[0] + x;
+                    full[start..start + size[0]].copy_from_slice(&pixels[row * size[0]..(row + 1) * size[0]]);
+                }
+
+                (previous.size, full)
+            },
+            _ => (size, pixels)
+        };
+
+        // `run` already waited for the GPU to be done with it
+        if let Some(previous) = previous {
+            self.destroy_texture(base, &previous);
+        }
+
+        let format = if self.srgb_framebuffer { vk::Format::R8G8B8A8_SRGB } else { vk::Format::R8G8B8A8_UNORM };
+        let rgba: Vec<u8> = pixels.iter().flat_map(|color| color.to_array()).collect();
+        let texture = Texture2D::from_rgba8(base, size[0] as u32, size[1] as u32, &rgba, format).expect("Failed to upload UI texture");
+
+        let set_layouts = [self.set_layout];
+        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(self.descriptor_pool)
+            .set_layouts(&set_layouts)
+            .build();
+        let descriptor_set = base.device.allocate_descriptor_sets(&allocate_info).expect("Too many UI textures")[0];
+
+        let image_info = vk::DescriptorImageInfo {
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            image_view: texture.data.view,
+            sampler: vk::Sampler::null()
+        };
+
+        let sampler_info = vk::DescriptorImageInfo {
+            sampler: self.samplers[delta.options.magnification as usize],
+            ..Default::default()
+        };
+
+        let writes = [
+            vk::WriteDescriptorSet {
+                dst_set: descriptor_set,
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::SAMPLED_IMAGE,
+                p_image_info: &image_info,
+                ..Default::default()
+            },
+            vk::WriteDescriptorSet {
+                dst_set: descriptor_set,
+                dst_binding: 1,
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::SAMPLER,
+                p_image_info: &sampler_info,
+                ..Default::default()
+            }
+        ];
+        base.device.update_descriptor_sets(&writes, &[]);
+
+        self.textures.insert(id, UiTexture { texture, descriptor_set, pixels, size });
+    }
+
+    unsafe fn destroy_texture(&self, base: &Base, texture: &UiTexture) {
+        base.device.free_descriptor_sets(self.descriptor_pool, &[texture.descriptor_set]).unwrap();
+        texture.texture.data.destroy(base);
+    }
+
+    /// Records the UI pass, which also moves the swapchain image to the present layout.
+    /// The draw command buffer fence must have been waited on, the vertex and index buffers get rewritten.
+    pub(crate) unsafe fn record(&mut self, base: &Base, command_buffer: vk::CommandBuffer, present_index: u32) {
+        let render_pass_begin_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(self.renderpass)
+            .framebuffer(self.framebuffers[present_index as usize])
+            .render_area(base.surface_resolution.into())
+            .build();
+
+        base.device.cmd_begin_render_pass(command_buffer, &render_pass_begin_info, vk::SubpassContents::INLINE);
+
+        if !self.primitives.is_empty() {
+            self.draw_primitives(base, command_buffer);
+        }
+
+        base.device.cmd_end_render_pass(command_buffer);
+    }
+
+    unsafe fn draw_primitives(&mut self, base: &Base, command_buffer: vk::CommandBuffer) {
+        let meshes: Vec<_> = self.primitives
+            .iter()
+            .filter_map(|primitive| match &primitive.primitive {
+                Primitive::Mesh(mesh) => Some((primitive.clip_rect, mesh)),
+                Primitive::Callback(_) => None
+            })
+            .collect();
+
+        let vertex_count: usize = meshes.iter().map(|(_, mesh)| mesh.vertices.len()).sum();
+        let index_count: usize = meshes.iter().map(|(_, mesh)| mesh.indices.len()).sum();
+
+        if index_count == 0 {
+            return;
+        }
+
+        let vertex_buffer = reserve_buffer(
+            base,
+            self.vertex_buffer.take(),
+            (vertex_count * std::mem::size_of::<egui::epaint::Vertex>()) as u64,
+            vk::BufferUsageFlags::VERTEX_BUFFER
+        );
+
+        let index_buffer = reserve_buffer(
+            base,
+            self.index_buffer.take(),
+            (index_count * std::mem::size_of::<u32>()) as u64,
+            vk::BufferUsageFlags::INDEX_BUFFER
+        );
+
+        let vertex_ptr = vertex_buffer.ptr.unwrap() as *mut egui::epaint::Vertex;
+        let index_ptr = index_buffer.ptr.unwrap() as *mut u32;
+
+        let device = &base.device;
+        let extent = base.surface_resolution;
+
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[vertex_buffer.buffer], &[0]);
+        device.cmd_bind_index_buffer(command_buffer, index_buffer.buffer, 0, vk::IndexType::UINT32);
+
+        device.cmd_set_viewport(command_buffer, 0, &[vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: extent.width as f32,
+            height: extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0
+        }]);
+
+        let push_constants = UiPushConstants {
+            screen_size: [extent.width as f32 / self.pixels_per_point, extent.height as f32 / self.pixels_per_point],
+            srgb_framebuffer: self.srgb_framebuffer as u32
+        };
+
+        cmd_push_constants(device, command_buffer, self.pipeline_layout, vk::ShaderStageFlags::VERTEX, 0, &push_constants);
+
+        let (mut first_vertex, mut first_index) = (0, 0);
+
+        for (clip_rect, mesh) in meshes {
+            std::ptr::copy_nonoverlapping(mesh.vertices.as_ptr(), vertex_ptr.add(first_vertex), mesh.vertices.len());
+            std::ptr::copy_nonoverlapping(mesh.indices.as_ptr(), index_ptr.add(first_index), mesh.indices.len());
+
+            // Clip rectangles are in points, scissors in pixels inside the framebuffer
+            let min_x = (clip_rect.min.x * self.pixels_per_point).round().clamp(0.0, extent.width as f32) as u32;
+            let min_y = (clip_rect.min.y * self.pixels_per_point).round().clamp(0.0, extent.height as f32) as u32;
+            let max_x = (clip_rect.max.x * self.pixels_per_point).round().clamp(0.0, extent.width as f32) as u32;
+            let max_y = (clip_rect.max.y * self.pixels_per_point).round().clamp(0.0, extent.height as f32) as u32;
+
+            if let Some(texture) = self.textures.get(&mesh.texture_id).filter(|_| max_x > min_x && max_y > min_y) {
+                device.cmd_set_scissor(command_buffer, 0, &[vk::Rect2D {
+                    offset: vk::Offset2D { x: min_x as i32, y: min_y as i32 },
+                    extent: vk::Extent2D { width: max_x - min_x, height: max_y - min_y }
+                }]);
+
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.pipeline_layout,
+                    0,
+                    &[texture.descriptor_set],
+                    &[]
+                );
+
+                device.cmd_draw_indexed(command_buffer, mesh.indices.len() as u32, 1, first_index as u32, first_vertex as i32, 0);
+            }
+
+            first_vertex += mesh.vertices.len();
+            first_index += mesh.indices.len();
+        }
+
+        self.vertex_buffer = Some(vertex_buffer);
+        self.index_buffer = Some(index_buffer);
+    }
+
+    pub unsafe fn destroy(&mut self, base: &Base) {
+        for texture in self.textures.values() {
+            self.destroy_texture(base, texture);
+        }
+
+        for buffer in self.vertex_buffer.iter().chain(self.index_buffer.iter()) {
+            buffer.destroy(base);
+        }
+
+        for &sampler in self.samplers.iter() {
+            base.device.destroy_sampler(sampler, None);
+        }
+
+        self.destroy_framebuffers(base);
+
+        base.device.destroy_pipeline(self.pipeline, None);
+        base.device.destroy_pipeline_layout(self.pipeline_layout, None);
+        base.device.destroy_descriptor_pool(self.descriptor_pool, None);
+        base.device.destroy_descriptor_set_layout(self.set_layout, None);
+        base.device.destroy_render_pass(self.renderpass, None);
+    }
+}
+
+/// Keeps `buffer` if it is large enough, otherwise replaces it with a mapped one of the next power of two size
+unsafe fn reserve_buffer(base: &Base, buffer: Option<Buffer>, size: u64, usage: vk::BufferUsageFlags) -> Buffer {
+    match buffer {
+        Some(buffer) if buffer.size >= size => buffer,
+        buffer => {
+            if let Some(buffer) = buffer {
+                buffer.destroy(base);
+            }
+
+            Buffer::new(
+                base,
+                size.next_power_of_two(),
+                usage,
+                vk::SharingMode::EXCLUSIVE,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                true
+            )
+        }
+    }
+}
+
+fn is_srgb(format: vk::Format) -> bool {
+    matches!(format, vk::Format::B8G8R8A8_SRGB | vk::Format::R8G8B8A8_SRGB | vk::Format::A8B8G8R8_SRGB_PACK32)
+}
+
+unsafe fn stats_window(context: &egui::Context, base: &Base, frame: &FrameContext, profiler: &Profiler) {
+    egui::Window::new("Tarsier").default_width(280.0).show(context, |ui| {
+        let frame_time = profiler.cpu_frame_time.as_secs_f64();
+        ui.label(format!("{:.0} fps, {:.2} ms", 1.0 / frame_time.max(f64::EPSILON), frame_time * 1000.0));
+
+        ui.collapsing("GPU passes", |ui| {
+            egui::Grid::new("passes").striped(true).show(ui, |ui| {
+                for timing in profiler.pass_timings.iter() {
+                    ui.label(timing.name);
+                    ui.label(format!("{:.3} ms", timing.gpu_time.as_secs_f64() * 1000.0));
+
+                    if let Some(statistics) = timing.statistics.filter(|statistics| statistics.compute_shader_invocations > 0) {
+                        ui.label(format!("{} cs", statistics.compute_shader_invocations));
+                    } else if let Some(statistics) = timing.statistics {
+                        ui.label(format!(
+                            "{} vs, {} prims, {} fs",
+                            statistics.vertex_shader_invocations,
+                            statistics.input_assembly_primitives,
+                            statistics.fragment_shader_invocations
+                        ));
+                    }
+
+                    ui.end_row();
+                }
+            });
+        });
+
+        ui.collapsing("Device", |ui| {
+            let properties = &base.device_properties;
+            let api_version = properties.api_version;
+
+            egui::Grid::new("device").show(ui, |ui| {
+                ui.label("Name");
+                ui.label(CStr::from_ptr(properties.device_name.as_ptr()).to_string_lossy());
+                ui.end_row();
+
+                ui.label("Type");
+                ui.label(format!("{:?}", properties.device_type));
+                ui.end_row();
+
+                ui.label("Vulkan");
+                ui.label(format!(
+                    "{}.{}.{}",
+                    vk::api_version_major(api_version),
+                    vk::api_version_minor(api_version),
+                    vk::api_version_patch(api_version)
+                ));
+                ui.end_row();
+
+                ui.label("Resolution");
+                ui.label(format!("{} x {}", base.surface_resolution.width, base.surface_resolution.height));
+                ui.end_row();
+
+                ui.label("Format");
+                ui.label(format!("{:?}", base.surface_format.format));
+                ui.end_row();
+
+                ui.label("MSAA");
+                ui.label(format!("{:?}", base.msaa_samples));
+                ui.end_row();
+
+                ui.label("Present mode");
+                ui.label(format!("{:?}", base.settings.present_mode));
+                ui.end_row();
+            });
+        });
+
+        ui.collapsing("Playback", |ui| {
+            let clock = frame.clock;
+
+            ui.label(format!("Time {:.2} s, frame {}", clock.time, clock.frame));
+            ui.label(format!("Speed x{}{}", clock.speed, if clock.paused { ", paused" } else { "" }));
+        });
+
+        ui.collapsing("Debug view", |ui| {
+            let debug_view = frame.debug_view;
+            let toggle = |enabled: bool| if enabled { "on" } else { "off" };
+
+            ui.label(format!("{} (F4 to F6)", debug_view.mode.name()));
+            ui.label(format!("Wireframe {} (F2), normals {} (F3)", toggle(debug_view.wireframe), toggle(debug_view.normals)));
+        });
+    });
+}
@@ -1,8 +1,12 @@
 use nalgebra_glm as glm;
 
+#[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct UniformBufferObject {
-    pub model: glm::Mat4,
     pub view: glm::Mat4,
     pub projection: glm::Mat4,
+    /// Multiplies the texture color
+    pub tint: glm::Vec4,
+    /// Size in pixels of the points drawn with `MeshTopology::Points`
+    pub point_size: f32,
 }
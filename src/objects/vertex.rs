@@ -7,14 +7,21 @@ use crate::offset_of;
 #[derive(Clone, Debug, Copy)]
 pub struct Vertex {
     pub pos: glm::Vec3,
-    pub uv: glm::Vec2
+    pub uv: glm::Vec2,
+    pub normal: glm::Vec3,
+    pub color: glm::Vec4,
+    /// xyz is the tangent, w the bitangent sign (bitangent = cross(normal, tangent) * w)
+    pub tangent: glm::Vec4
 }
 
 impl Vertex {
-    pub fn new(pos: glm::Vec3, uv: glm::Vec2) -> Self {
-        Vertex { pos, uv }
+    pub fn new(pos: glm::Vec3, uv: glm::Vec2, normal: glm::Vec3, color: glm::Vec4) -> Self {
+        Vertex {
+            pos, uv, normal, color,
+            tangent: glm::vec4(1.0, 0.0, 0.0, 1.0)
+        }
     }
-    
+
     pub fn binding_description() -> vk::VertexInputBindingDescription {
         vk::VertexInputBindingDescription {
             binding: 0,
@@ -23,7 +30,7 @@ impl Vertex {
         }
     }
 
-    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 2] {
+    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 5] {
         [
             vk::VertexInputAttributeDescription {
                 binding: 0,
@@ -37,14 +44,48 @@ impl Vertex {
                 location: 1,
                 format: vk::Format::R32G32_SFLOAT,
                 offset: offset_of!(Vertex, uv) as u32,
+            },
+
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 2,
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: offset_of!(Vertex, normal) as u32,
+            },
+
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 3,
+                format: vk::Format::R32G32B32A32_SFLOAT,
+                offset: offset_of!(Vertex, color) as u32,
+            },
+
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 4,
+                format: vk::Format::R32G32B32A32_SFLOAT,
+                offset: offset_of!(Vertex, tangent) as u32,
             }
         ]
     }
+
+    /// pos, uv, normal, color and tangent, one float after the other
+    pub(crate) fn components(&self) -> impl Iterator<Item = &f32> {
+        self.pos.iter()
+            .chain(self.uv.iter())
+            .chain(self.normal.iter())
+            .chain(self.color.iter())
+            .chain(self.tangent.iter())
+    }
 }
 
 impl PartialEq for Vertex {
     fn eq(&self, other: &Self) -> bool {
-        self.pos == other.pos && self.uv == other.uv
+        self.pos == other.pos
+            && self.uv == other.uv
+            && self.normal == other.normal
+            && self.color == other.color
+            && self.tangent == other.tangent
     }
 }
 
@@ -52,10 +93,8 @@ impl Eq for Vertex {}
 
 impl Hash for Vertex {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.pos[0].to_bits().hash(state);
-        self.pos[1].to_bits().hash(state);
-        self.pos[2].to_bits().hash(state);
-        self.uv[0].to_bits().hash(state);
-        self.uv[1].to_bits().hash(state);
+        for component in self.components() {
+            component.to_bits().hash(state);
+        }
     }
-}
\ No newline at end of file
+}
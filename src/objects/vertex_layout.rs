@@ -0,0 +1,233 @@
+use std::error::Error;
+
+use ash::vk;
+
+use crate::{ShaderReflection, Vertex};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VertexSemantic {
+    Position,
+    Uv,
+    Normal,
+    Color,
+    Tangent,
+    /// Not backed by mesh data, e.g. per-instance attributes filled by the app
+    Custom(String)
+}
+
+impl VertexSemantic {
+    /// Guesses the semantic from a shader input name, e.g. `pos`, `in_normal` or `a_texcoord`
+    pub fn from_name(name: &str) -> Self {
+        let lowercase = name.to_lowercase();
+
+        if lowercase.contains("tangent") {
+            VertexSemantic::Tangent
+        } else if lowercase.contains("normal") {
+            VertexSemantic::Normal
+        } else if lowercase.contains("uv") || lowercase.contains("texcoord") {
+            VertexSemantic::Uv
+        } else if lowercase.contains("color") || lowercase.contains("colour") {
+            VertexSemantic::Color
+        } else if lowercase.contains("pos") {
+            VertexSemantic::Position
+        } else {
+            VertexSemantic::Custom(name.to_string())
+        }
+    }
+
+    fn components(&self, vertex: &Vertex) -> [f32; 4] {
+        match self {
+            VertexSemantic::Position => [vertex.pos.x, vertex.pos.y, vertex.pos.z, 1.0],
+            VertexSemantic::Uv => [vertex.uv.x, vertex.uv.y, 0.0, 0.0],
+            VertexSemantic::Normal => [vertex.normal.x, vertex.normal.y, vertex.normal.z, 0.0],
+            VertexSemantic::Color => [vertex.color.x, vertex.color.y, vertex.color.z, vertex.color.w],
+            VertexSemantic::Tangent => [vertex.tangent.x, vertex.tangent.y, vertex.tangent.z, vertex.tangent.w],
+            VertexSemantic::Custom(_) => [0.0; 4]
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub binding: u32,
+    pub format: vk::Format,
+    pub offset: u32,
+    pub semantic: VertexSemantic
+}
+
+#[derive(Clone, Debug)]
+pub struct VertexBinding {
+    pub binding: u32,
+    pub stride: u32,
+    pub input_rate: vk::VertexInputRate
+}
+
+/// Describes how vertex data is laid out in the vertex buffers bound to a pipeline
+#[derive(Clone, Debug, Default)]
+pub struct VertexLayout {
+    pub bindings: Vec<VertexBinding>,
+    pub attributes: Vec<VertexAttribute>
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an attribute right after the previous ones of the same binding, creating the binding if needed.
+    /// Fails on formats other than the 32 bit per component ones, see `format_size`.
+    pub fn attribute(
+        mut self,
+        binding: u32,
+        input_rate: vk::VertexInputRate,
+        location: u32,
+        format: vk::Format,
+        semantic: VertexSemantic
+    ) -> Result<Self, Box<dyn Error>> {
+        let size = format_size(format).ok_or_else(|| format!("Unsupported vertex attribute format {format:?}"))?;
+
+        let binding_index = match self.bindings.iter().position(|b| b.binding == binding) {
+            Some(index) => index,
+            None => {
+                self.bindings.push(VertexBinding { binding, stride: 0, input_rate });
+                self.bindings.len() - 1
+            }
+        };
+
+        let vertex_binding = &mut self.bindings[binding_index];
+
+        self.attributes.push(VertexAttribute {
+            location,
+            binding,
+            format,
+            offset: vertex_binding.stride,
+            semantic
+        });
+
+        vertex_binding.stride += size;
+        Ok(self)
+    }
+
+    /// Builds the layout the vertex shader expects: inputs named `instance_*` go to a per-instance buffer at binding 1,
+    /// everything else is interleaved in binding 0 and matched to the mesh data by name, see `VertexSemantic::from_name`
+    pub fn from_reflection(reflection: &ShaderReflection) -> Result<Self, Box<dyn Error>> {
+        reflection.inputs.iter().try_fold(VertexLayout::new(), |layout, input| {
+            if input.name.starts_with("instance") {
+                layout.attribute(
+                    1,
+                    vk::VertexInputRate::INSTANCE,
+                    input.location,
+                    input.format,
+                    VertexSemantic::Custom(input.name.clone())
+                )
+            } else {
+                layout.attribute(
+                    0,
+                    vk::VertexInputRate::VERTEX,
+                    input.location,
+                    input.format,
+                    VertexSemantic::from_name(&input.name)
+                )
+            }
+        })
+    }
+
+    /// The layout matching `Vertex` as a whole
+    pub fn full() -> Self {
+        Vertex::attribute_descriptions().iter().zip([
+            VertexSemantic::Position,
+            VertexSemantic::Uv,
+            VertexSemantic::Normal,
+            VertexSemantic::Color,
+            VertexSemantic::Tangent
+        ]).fold(VertexLayout::new(), |layout, (description, semantic)| {
+            layout
+                .attribute(0, vk::VertexInputRate::VERTEX, description.location, description.format, semantic)
+                .expect("Vertex attributes are 32 bit per component")
+        })
+    }
+
+    pub fn binding_descriptions(&self) -> Vec<vk::VertexInputBindingDescription> {
+        self.bindings
+            .iter()
+            .map(|binding| vk::VertexInputBindingDescription {
+                binding: binding.binding,
+                stride: binding.stride,
+                input_rate: binding.input_rate
+            })
+            .collect()
+    }
+
+    pub fn attribute_descriptions(&self) -> Vec<vk::VertexInputAttributeDescription> {
+        self.attributes
+            .iter()
+            .map(|attribute| vk::VertexInputAttributeDescription {
+                location: attribute.location,
+                binding: attribute.binding,
+                format: attribute.format,
+                offset: attribute.offset
+            })
+            .collect()
+    }
+
+    /// Packs the mesh data into the interleaved layout of `binding`, ready to be copied into a vertex buffer
+    pub fn pack(&self, binding: u32, vertices: &[Vertex]) -> Vec<u8> {
+        let stride = self.bindings
+            .iter()
+            .find(|b| b.binding == binding)
+            .map_or(0, |b| b.stride) as usize;
+
+        let attributes: Vec<&VertexAttribute> = self.attributes
+            .iter()
+            .filter(|attribute| attribute.binding == binding)
+            .collect();
+
+        let mut data = vec![0u8; stride * vertices.len()];
+
+        for (vertex, chunk) in vertices.iter().zip(data.chunks_exact_mut(stride.max(1))) {
+            for attribute in attributes.iter() {
+                let components = attribute.semantic.components(vertex);
+                let count = format_size(attribute.format).map_or(0, |size| size / 4) as usize;
+
+                for (index, &component) in components.iter().take(count).enumerate() {
+                    let bytes = match format_kind(attribute.format) {
+                        FormatKind::Float => component.to_le_bytes(),
+                        FormatKind::Sint => (component as i32).to_le_bytes(),
+                        FormatKind::Uint => (component as u32).to_le_bytes()
+                    };
+
+                    let start = attribute.offset as usize + index * 4;
+                    chunk[start..start + 4].copy_from_slice(&bytes);
+                }
+            }
+        }
+
+        data
+    }
+}
+
+enum FormatKind {
+    Float,
+    Sint,
+    Uint
+}
+
+fn format_kind(format: vk::Format) -> FormatKind {
+    match format {
+        vk::Format::R32_SINT | vk::Format::R32G32_SINT | vk::Format::R32G32B32_SINT | vk::Format::R32G32B32A32_SINT => FormatKind::Sint,
+        vk::Format::R32_UINT | vk::Format::R32G32_UINT | vk::Format::R32G32B32_UINT | vk::Format::R32G32B32A32_UINT => FormatKind::Uint,
+        _ => FormatKind::Float
+    }
+}
+
+/// Size in bytes of the 32 bit per component formats used for vertex attributes, `None` for any other format
+pub fn format_size(format: vk::Format) -> Option<u32> {
+    match format {
+        vk::Format::R32_SFLOAT | vk::Format::R32_SINT | vk::Format::R32_UINT => Some(4),
+        vk::Format::R32G32_SFLOAT | vk::Format::R32G32_SINT | vk::Format::R32G32_UINT => Some(8),
+        vk::Format::R32G32B32_SFLOAT | vk::Format::R32G32B32_SINT | vk::Format::R32G32B32_UINT => Some(12),
+        vk::Format::R32G32B32A32_SFLOAT | vk::Format::R32G32B32A32_SINT | vk::Format::R32G32B32A32_UINT => Some(16),
+        _ => None
+    }
+}